@@ -0,0 +1,41 @@
+use crate::dialogue::expected::Expected;
+use rhai::{FnPtr, Map};
+use std::cell::RefCell;
+
+/// What a skill asked the user for with the `ask` module.
+#[derive(Clone)]
+pub(crate) enum AskKind {
+    /// `ask.question`: a response key of the skill, with its placeholders
+    Question {
+        key: String,
+        context: Map,
+        expected: Expected,
+    },
+    /// `ask.on_input`: the next utterance, the skill already said what it wants
+    Input { expected: Expected },
+    /// `ask.confirm`: yes or no
+    Confirm,
+    /// `ask.number_input`
+    Number { prompt: String },
+    /// `ask.cancel`: not a question, called when the interaction is cancelled
+    Cancel,
+}
+
+#[derive(Clone)]
+pub(crate) struct AskRequest {
+    pub(crate) kind: AskKind,
+    pub(crate) callback: FnPtr,
+}
+
+thread_local! {
+    /// Requests made by the script running right now, collected by the dialogue manager
+    static REQUESTS: RefCell<Vec<AskRequest>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn request(kind: AskKind, callback: FnPtr) {
+    REQUESTS.with(|requests| requests.borrow_mut().push(AskRequest { kind, callback }));
+}
+
+pub(crate) fn take_requests() -> Vec<AskRequest> {
+    REQUESTS.with(|requests| requests.borrow_mut().drain(..).collect())
+}
@@ -0,0 +1,110 @@
+use regex::Regex;
+use rhai::Dynamic;
+
+/// The `expected` argument of `ask.question` and `ask.on_input`: what an answer must be and
+/// what the callback receives for it.
+#[derive(Clone)]
+pub(crate) enum Expected {
+    /// Any answer, as a string
+    Text,
+    Int,
+    Float,
+    /// A yes or no answer, as `true` or `false`
+    Bool,
+    /// A date, as the map of the `date` slot
+    Date,
+    /// One of the values, compared case-insensitively, as the value itself
+    OneOf(Vec<Dynamic>),
+    /// Written `/regex/`, the answer must match it
+    Pattern(Regex),
+    /// A shared slot, `default/name` or `entity/name`, as its typed value
+    Slot(String),
+}
+
+impl Expected {
+    pub(crate) fn parse(expected: &Dynamic) -> Result<Self, String> {
+        if expected.is_unit() {
+            return Ok(Expected::Text);
+        }
+
+        if expected.is_array() {
+            let values = expected.clone().into_array().unwrap_or_default();
+            if values.is_empty() {
+                return Err("The list of expected values is empty".to_string());
+            }
+            return Ok(Expected::OneOf(values));
+        }
+
+        let Ok(name) = expected.clone().into_string() else {
+            return Err(format!(
+                "Expected a type name, a list, a /regex/ or a slot, got {}",
+                expected.type_name()
+            ));
+        };
+
+        if let Some(pattern) = name
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            return Regex::new(pattern)
+                .map(Expected::Pattern)
+                .map_err(|err| format!("Invalid expected regex {}: {}", name, err));
+        }
+
+        match name.as_str() {
+            "" | "text" => Ok(Expected::Text),
+            "int" => Ok(Expected::Int),
+            "float" => Ok(Expected::Float),
+            "bool" => Ok(Expected::Bool),
+            "date" => Ok(Expected::Date),
+            _ if name.starts_with("default/") || name.starts_with("entity/") => {
+                Ok(Expected::Slot(name))
+            }
+            _ => Err(format!(
+                "Unknown expected type {} (text, int, float, bool, date, a list, a /regex/, \
+                 default/<slot> or entity/<entity>)",
+                name
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhai::Array;
+
+    fn parse(expected: impl Into<Dynamic>) -> Result<Expected, String> {
+        Expected::parse(&expected.into())
+    }
+
+    #[test]
+    fn parses_type_names() {
+        assert!(matches!(parse(()), Ok(Expected::Text)));
+        assert!(matches!(parse(""), Ok(Expected::Text)));
+        assert!(matches!(parse("text"), Ok(Expected::Text)));
+        assert!(matches!(parse("int"), Ok(Expected::Int)));
+        assert!(matches!(parse("float"), Ok(Expected::Float)));
+        assert!(matches!(parse("bool"), Ok(Expected::Bool)));
+        assert!(matches!(parse("date"), Ok(Expected::Date)));
+        assert!(parse("integer").is_err());
+        assert!(parse(42_i64).is_err());
+    }
+
+    #[test]
+    fn parses_lists_regexes_and_slots() {
+        let values: Array = vec!["red".into(), "blue".into()];
+        assert!(matches!(parse(values), Ok(Expected::OneOf(v)) if v.len() == 2));
+        assert!(parse(Array::new()).is_err());
+
+        match parse("/^[a-z]{3}$/") {
+            Ok(Expected::Pattern(regex)) => assert!(regex.is_match("abc")),
+            _ => panic!("expected a pattern"),
+        }
+        assert!(parse("/[a-z/").is_err());
+
+        assert!(matches!(parse("default/number"), Ok(Expected::Slot(s)) if s == "default/number"));
+        assert!(matches!(parse("entity/city"), Ok(Expected::Slot(s)) if s == "entity/city"));
+        assert!(parse("intent/city").is_err());
+    }
+}
@@ -0,0 +1,656 @@
+use crate::dialogue::ask::{AskKind, take_requests};
+use crate::dialogue::expected::Expected;
+use crate::dialogue::session::{Pending, Session};
+use crate::intent::engine::{IntentEngine, SkillContext};
+use crate::intent::processor::ProcessorLoader;
+use crate::intent::recognizer::Recognizer;
+use crate::intent::slot_extrator::ExtractedSlots;
+use crate::skills::avi_script::language::response::Response;
+use crate::skills::error::SkillError;
+use crate::skills::manager::SkillManager;
+use rhai::{Dynamic, Engine};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How long a question waits for its answer unless `set_timeout` changes it
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Times an answer that doesn't fit is asked again before the interaction is cancelled
+const DEFAULT_MAX_REPROMPTS: u32 = 2;
+
+/// The yes, no and cancel intents, in every language the core answers in
+const ANSWER_INTENTS: [&str; 3] = [
+    include_str!("intents/yes.intent"),
+    include_str!("intents/no.intent"),
+    include_str!("intents/cancel.intent"),
+];
+
+/// What the core says itself, in every language it answers in
+const RESPONSES: [&str; 5] = [
+    include_str!("responses/cancelled.resp"),
+    include_str!("responses/not_understood.resp"),
+    include_str!("responses/not_understood_answer.resp"),
+    include_str!("responses/gave_up.resp"),
+    include_str!("responses/yes_or_no.resp"),
+];
+
+/// Language of the built-in responses when they have nothing for the current one
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// Sits between the recognizer and the skills, keeping a session per user or device. An
+/// utterance answers the question pending in its session, if any, and is recognized
+/// otherwise.
+pub struct DialogueManager<'a> {
+    recognizer: Recognizer<'a>,
+    sessions: HashMap<String, Session>,
+    timeout: Duration,
+    max_reprompts: u32,
+    /// Recognizes `ANSWER_INTENTS`, only while a question is pending
+    answers: IntentEngine,
+    /// `RESPONSES` by id
+    responses: HashMap<String, Response>,
+}
+
+impl<'a> DialogueManager<'a> {
+    /// The answer intents follow the language and normalization the recognizer's engine has
+    /// at this point.
+    pub fn new(recognizer: Recognizer<'a>) -> Self {
+        let mut answers = IntentEngine::new();
+        answers.set_normalizer_config(recognizer.normalizer_config().clone());
+        answers.set_language(recognizer.language());
+
+        let processors = ProcessorLoader::new(Rc::new(Engine::new_raw()), PathBuf::new());
+        let context = SkillContext {
+            skill_id: "avi.dialogue",
+            languages: &["en".to_string(), "pt".to_string()],
+            processors: &processors,
+        };
+        for content in ANSWER_INTENTS {
+            if let Err(err) = answers.load_intent_from_str(content, None, &context) {
+                eprintln!("Error loading the built-in answer intents: {}", err);
+            }
+        }
+        answers.prepare();
+
+        let mut responses = HashMap::new();
+        for content in RESPONSES {
+            match serde_json::from_str::<Response>(content) {
+                Ok(response) if response.is_valid() => {
+                    responses.insert(response.id.clone(), response);
+                }
+                Ok(response) => eprintln!("Built-in response {} has no text", response.id),
+                Err(err) => eprintln!("Error loading the built-in responses: {}", err),
+            }
+        }
+
+        DialogueManager {
+            recognizer,
+            sessions: HashMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+            max_reprompts: DEFAULT_MAX_REPROMPTS,
+            answers,
+            responses,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_max_reprompts(&mut self, max_reprompts: u32) {
+        self.max_reprompts = max_reprompts;
+    }
+
+    /// A built-in response in the current language, the id itself if there is none.
+    fn say(&self, id: &str) -> String {
+        let Some(response) = self.responses.get(id) else {
+            return id.to_string();
+        };
+        [self.recognizer.language(), FALLBACK_LANGUAGE]
+            .iter()
+            .find_map(|language| response.response.get(*language)?.first())
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// Whether the session waits for an answer, so its next utterance is not a new request.
+    pub fn is_active(&self, session_id: &str) -> bool {
+        self.sessions
+            .get(session_id)
+            .is_some_and(Session::is_active)
+    }
+
+    /// Cancels the interactions left unanswered for longer than the timeout.
+    pub fn expire(&mut self, skills: &mut SkillManager) {
+        let timeout = self.timeout;
+        for session in self.sessions.values_mut() {
+            if session.is_active() && session.last_activity.elapsed() > timeout {
+                cancel(session, skills);
+            }
+        }
+    }
+
+    /// Handles an utterance of a session and returns what to say to the user.
+    pub fn handle(
+        &mut self,
+        session_id: &str,
+        utterance: &str,
+        skills: &mut SkillManager,
+    ) -> Vec<String> {
+        self.expire(skills);
+
+        let mut session = self
+            .sessions
+            .remove(session_id)
+            .unwrap_or_else(Session::new);
+        if session.last_activity.elapsed() > self.timeout {
+            session.last_intent = None;
+        }
+        session.last_activity = Instant::now();
+        let mut replies = Vec::new();
+
+        match session.pending.take() {
+            Some(_) if self.answer_intent(utterance).as_deref() == Some("cancel") => {
+                cancel(&mut session, skills);
+                replies.push(self.say("cancelled"));
+            }
+            Some(pending) => self.answer(&mut session, pending, utterance, skills, &mut replies),
+            None => {
+                // Follow-ups of the last intent take precedence while they are in context
+                let follow_up = session
+                    .last_intent
+                    .as_ref()
+                    .and_then(|previous| self.recognizer.recognize_follow_up(utterance, previous));
+                let intents = match follow_up {
+                    Some(intent) => vec![intent],
+                    None => self.recognizer.recognize_all(utterance),
+                };
+
+                if intents.is_empty() {
+                    replies.push(self.say("not_understood"));
+                } else {
+                    session.queue.extend(intents);
+                    self.advance(&mut session, skills, &mut replies);
+                }
+            }
+        }
+
+        self.sessions.insert(session_id.to_string(), session);
+        replies
+    }
+
+    /// Dispatches intents recognized outside `handle`, like an early end-pointed utterance.
+    pub fn dispatch(
+        &mut self,
+        session_id: &str,
+        intents: Vec<ExtractedSlots>,
+        skills: &mut SkillManager,
+    ) -> Vec<String> {
+        let mut session = self
+            .sessions
+            .remove(session_id)
+            .unwrap_or_else(Session::new);
+        session.last_activity = Instant::now();
+        session.queue.extend(intents);
+
+        let mut replies = Vec::new();
+        self.advance(&mut session, skills, &mut replies);
+        self.sessions.insert(session_id.to_string(), session);
+        replies
+    }
+
+    /// Acts on a transcript that is still being said once its intent is clear: the only
+    /// complete match, with no longer pattern still able to match. `None` waits for more, as it
+    /// does while a question is pending, since answers are taken from final transcripts.
+    pub fn handle_partial(
+        &mut self,
+        session_id: &str,
+        partial: &str,
+        skills: &mut SkillManager,
+    ) -> Option<Vec<String>> {
+        if self.is_active(session_id) {
+            return None;
+        }
+
+        let recognition = self.recognizer.recognize_partial(partial);
+        let intent = recognition.unambiguous()?.clone();
+        Some(self.dispatch(session_id, vec![intent], skills))
+    }
+
+    /// `yes`, `no` or `cancel` when the utterance is one of those answers. Only the patterns
+    /// decide, the classifier would take "not sure" for a yes. An utterance matching more than
+    /// one answer is none of them and gets asked again.
+    fn answer_intent(&self, utterance: &str) -> Option<String> {
+        let answers = Recognizer::new(&self.answers).recognize_with(utterance, false);
+        let first = answers.first()?;
+        answers
+            .iter()
+            .all(|answer| answer.intent == first.intent)
+            .then(|| first.intent.clone())
+    }
+
+    /// Keeps the question pending and asks it again, unless it was asked too many times.
+    fn reprompt(
+        &self,
+        session: &mut Session,
+        pending: Pending,
+        text: String,
+        skills: &mut SkillManager,
+        replies: &mut Vec<String>,
+    ) {
+        if session.reprompts >= self.max_reprompts {
+            cancel(session, skills);
+            replies.push(self.say("gave_up"));
+            return;
+        }
+
+        session.reprompts += 1;
+        session.pending = Some(pending);
+        replies.push(text);
+    }
+
+    fn answer(
+        &self,
+        session: &mut Session,
+        pending: Pending,
+        utterance: &str,
+        skills: &mut SkillManager,
+        replies: &mut Vec<String>,
+    ) {
+        match pending {
+            Pending::Slot {
+                mut intent,
+                slot,
+                prompt,
+            } => {
+                if !self.recognizer.fill_slot(&mut intent, &slot, utterance) {
+                    let text = skills.response(&intent.intent, &prompt, &intent.language);
+                    let pending = Pending::Slot {
+                        intent,
+                        slot,
+                        prompt,
+                    };
+                    self.reprompt(session, pending, text, skills, replies);
+                    return;
+                }
+                session.queue.push_front(intent);
+            }
+            Pending::Ask { intent, request } => {
+                let Some(value) = self.parse_answer(&request.kind, utterance) else {
+                    let text = match request.kind {
+                        AskKind::Confirm
+                        | AskKind::Input {
+                            expected: Expected::Bool,
+                        } => self.say("yes_or_no"),
+                        _ => prompt(&request.kind, &intent, skills)
+                            .unwrap_or_else(|| self.say("not_understood_answer")),
+                    };
+                    self.reprompt(
+                        session,
+                        Pending::Ask { intent, request },
+                        text,
+                        skills,
+                        replies,
+                    );
+                    return;
+                };
+
+                let result = skills.call_back(&intent.intent, &request.callback, vec![value]);
+                self.collect_requests(session, &intent, result, skills, replies);
+            }
+        }
+
+        self.advance(session, skills, replies);
+    }
+
+    /// Value given to the callback of the question, `None` to ask again.
+    fn parse_answer(&self, kind: &AskKind, utterance: &str) -> Option<Dynamic> {
+        match kind {
+            AskKind::Question { expected, .. } | AskKind::Input { expected } => {
+                self.convert(expected, utterance)
+            }
+            AskKind::Confirm => self.convert(&Expected::Bool, utterance),
+            AskKind::Number { .. } => self.recognizer.parse_answer("default/number", utterance),
+            AskKind::Cancel => None,
+        }
+    }
+
+    /// Validates an answer against the `expected` of its question and converts it.
+    fn convert(&self, expected: &Expected, utterance: &str) -> Option<Dynamic> {
+        let text = utterance.trim();
+        match expected {
+            Expected::Text => (!text.is_empty()).then(|| text.to_string().into()),
+            Expected::Int => {
+                let number = self.recognizer.parse_answer("default/number", text)?;
+                match number.as_float() {
+                    Ok(float) if float.fract() == 0.0 => Some((float as i64).into()),
+                    Ok(_) => None,
+                    Err(_) => Some(number),
+                }
+            }
+            Expected::Float => {
+                let number = self.recognizer.parse_answer("default/number", text)?;
+                match number.as_int() {
+                    Ok(int) => Some((int as f64).into()),
+                    Err(_) => Some(number),
+                }
+            }
+            Expected::Bool => match self.answer_intent(text)?.as_str() {
+                "yes" => Some(true.into()),
+                "no" => Some(false.into()),
+                _ => None,
+            },
+            Expected::Date => self.recognizer.parse_answer("default/date", text),
+            Expected::OneOf(values) => {
+                let comparable = |text: &str| self.recognizer.normalize(text).to_lowercase();
+                let answer = comparable(text);
+                values
+                    .iter()
+                    .find(|value| comparable(&value.to_string()) == answer)
+                    .cloned()
+            }
+            Expected::Pattern(regex) => regex.is_match(text).then(|| text.to_string().into()),
+            Expected::Slot(slot) => self.recognizer.parse_answer(slot, text),
+        }
+    }
+
+    /// Dispatches the queued intents until one waits for an answer.
+    fn advance(&self, session: &mut Session, skills: &mut SkillManager, replies: &mut Vec<String>) {
+        while session.pending.is_none() {
+            let Some(intent) = session.queue.pop_front() else {
+                // The interaction is over, its cancel handler with it
+                session.cancel = None;
+                break;
+            };
+
+            if let Some((slot, prompt)) = self.recognizer.missing_slots(&intent).into_iter().next()
+            {
+                replies.push(skills.response(&intent.intent, &prompt, &intent.language));
+                session.reprompts = 0;
+                session.pending = Some(Pending::Slot {
+                    intent,
+                    slot,
+                    prompt,
+                });
+                break;
+            }
+
+            let result = skills.process_intent(intent.clone());
+            if result.is_ok() {
+                session.last_intent = Some(intent.clone());
+            }
+            self.collect_requests(session, &intent, result, skills, replies);
+        }
+    }
+
+    /// Takes the `ask` requests the script of `intent` just made.
+    fn collect_requests(
+        &self,
+        session: &mut Session,
+        intent: &ExtractedSlots,
+        result: Result<(), SkillError>,
+        skills: &mut SkillManager,
+        replies: &mut Vec<String>,
+    ) {
+        let requests = take_requests();
+        if let Err(err) = result {
+            eprintln!("Error processing the intent: {}", err);
+            return;
+        }
+
+        for request in requests {
+            if let AskKind::Cancel = request.kind {
+                session.cancel = Some((intent.clone(), request.callback));
+                continue;
+            }
+
+            if session.pending.is_some() {
+                eprintln!(
+                    "Ignoring a question of intent {}, one is already pending",
+                    intent.intent
+                );
+                continue;
+            }
+
+            if let AskKind::Question { expected, .. } | AskKind::Input { expected } = &request.kind
+                && let Expected::Slot(slot) = expected
+                && !self.recognizer.knows_slot(slot)
+            {
+                eprintln!(
+                    "Intent {} expects an answer for {}, which is not defined",
+                    intent.intent, slot
+                );
+            }
+
+            if let Some(text) = prompt(&request.kind, intent, skills) {
+                replies.push(text);
+            }
+            session.reprompts = 0;
+            session.pending = Some(Pending::Ask {
+                intent: intent.clone(),
+                request,
+            });
+        }
+    }
+}
+
+/// Text asking the question, `None` when the skill says it itself.
+fn prompt(kind: &AskKind, intent: &ExtractedSlots, skills: &SkillManager) -> Option<String> {
+    match kind {
+        AskKind::Question { key, context, .. } => {
+            let mut text = skills.response(&intent.intent, key, &intent.language);
+            for (name, value) in context {
+                text = text.replace(&format!("{{{}}}", name), &value.to_string());
+            }
+            Some(text)
+        }
+        AskKind::Number { prompt } => Some(prompt.clone()),
+        AskKind::Input { .. } | AskKind::Confirm | AskKind::Cancel => None,
+    }
+}
+
+/// Ends the interaction of the session and runs its `ask.cancel` handler.
+fn cancel(session: &mut Session, skills: &mut SkillManager) {
+    if let Some((intent, callback)) = session.clear() {
+        if let Err(err) = skills.call_back(&intent.intent, &callback, Vec::new()) {
+            eprintln!("Error cancelling intent {}: {}", intent.intent, err);
+        }
+        // Nothing is pending anymore for a cancel handler to ask
+        take_requests();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intent::engine::tests::load;
+    use crate::skills::manager::tests::working_dir_lock;
+    use regex::Regex;
+
+    fn convert(expected: &Expected, utterance: &str) -> Option<Dynamic> {
+        let engine = IntentEngine::new();
+        let manager = DialogueManager::new(Recognizer::new(&engine));
+        manager.convert(expected, utterance)
+    }
+
+    #[test]
+    fn converts_numbers() {
+        assert_eq!(
+            convert(&Expected::Int, "forty two").unwrap().as_int(),
+            Ok(42)
+        );
+        assert_eq!(convert(&Expected::Int, "3.0").unwrap().as_int(), Ok(3));
+        assert!(convert(&Expected::Int, "3.5").is_none());
+        assert!(convert(&Expected::Int, "lots").is_none());
+        assert_eq!(
+            convert(&Expected::Float, "3.5").unwrap().as_float(),
+            Ok(3.5)
+        );
+        assert_eq!(
+            convert(&Expected::Float, "two").unwrap().as_float(),
+            Ok(2.0)
+        );
+    }
+
+    #[test]
+    fn converts_text_choices_and_patterns() {
+        assert_eq!(
+            convert(&Expected::Text, "  anything  ")
+                .unwrap()
+                .into_string(),
+            Ok("anything".to_string())
+        );
+        assert!(convert(&Expected::Text, " ").is_none());
+
+        let colors = Expected::OneOf(vec!["Red".into(), "Blue".into()]);
+        assert_eq!(
+            convert(&colors, "red").unwrap().into_string(),
+            Ok("Red".to_string())
+        );
+        assert!(convert(&colors, "green").is_none());
+
+        let code = Expected::Pattern(Regex::new("^[0-9]{4}$").unwrap());
+        assert!(convert(&code, "1234").is_some());
+        assert!(convert(&code, "12345").is_none());
+    }
+
+    #[test]
+    fn converts_yes_no_dates_and_slots() {
+        assert_eq!(
+            convert(&Expected::Bool, "yes please").unwrap().as_bool(),
+            Ok(true)
+        );
+        assert_eq!(
+            convert(&Expected::Bool, "nope").unwrap().as_bool(),
+            Ok(false)
+        );
+        assert!(convert(&Expected::Bool, "maybe").is_none());
+
+        assert!(convert(&Expected::Date, "tomorrow").unwrap().is_map());
+        assert!(convert(&Expected::Date, "whenever").is_none());
+
+        let slot = Expected::Slot("default/duration".to_string());
+        assert!(convert(&slot, "ten minutes").unwrap().is_map());
+        assert!(convert(&slot, "soon").is_none());
+    }
+
+    #[test]
+    fn answers_match_by_pattern_only() {
+        let engine = IntentEngine::new();
+        let manager = DialogueManager::new(Recognizer::new(&engine));
+        let answer = |text| manager.answer_intent(text);
+
+        assert_eq!(answer("yes").as_deref(), Some("yes"));
+        assert_eq!(answer("sure please").as_deref(), Some("yes"));
+        assert_eq!(answer("no thanks").as_deref(), Some("no"));
+        assert_eq!(answer("never mind").as_deref(), Some("cancel"));
+        assert_eq!(answer("cancel that").as_deref(), Some("cancel"));
+        assert_eq!(answer("not sure"), None);
+        assert_eq!(answer("yes and no"), None);
+    }
+
+    #[test]
+    fn answers_follow_the_language() {
+        let mut engine = IntentEngine::new();
+        engine.set_language("pt");
+        let manager = DialogueManager::new(Recognizer::new(&engine));
+
+        assert_eq!(manager.answer_intent("sim").as_deref(), Some("yes"));
+        assert_eq!(
+            manager.answer_intent("cancela isso").as_deref(),
+            Some("cancel")
+        );
+    }
+
+    #[test]
+    fn replies_follow_the_language() {
+        let mut engine = IntentEngine::new();
+        engine.set_language("pt");
+        let mut skills = SkillManager::new();
+        let mut manager = DialogueManager::new(Recognizer::new(&engine));
+
+        assert_eq!(
+            manager.handle("s", "abre a janela", &mut skills),
+            vec!["Desculpe, não entendi."]
+        );
+        assert_eq!(manager.say("yes_or_no"), "Por favor, responda sim ou não.");
+
+        let mut engine = IntentEngine::new();
+        engine.set_language("fr");
+        let manager = DialogueManager::new(Recognizer::new(&engine));
+        assert_eq!(manager.say("yes_or_no"), "Please answer yes or no.");
+    }
+
+    #[test]
+    fn missing_slots_are_asked_for_until_cancelled() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "patterns": ["book a flight [to {default/locations}]"], "required_slots": {"default/locations": "ask_destination"}}"#,
+        );
+        let mut skills = SkillManager::new();
+        let mut manager = DialogueManager::new(Recognizer::new(&engine));
+
+        assert_eq!(
+            manager.handle("s", "book a flight", &mut skills),
+            vec!["ask_destination"]
+        );
+        assert!(manager.is_active("s"));
+        assert_eq!(
+            manager.handle("s", "cancel", &mut skills),
+            vec!["Okay, never mind."]
+        );
+        assert!(!manager.is_active("s"));
+    }
+
+    #[test]
+    fn unfitting_answers_are_asked_again_then_given_up() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "timer", "patterns": ["set a timer [for {default/duration}]"], "required_slots": {"default/duration": "ask_duration"}}"#,
+        );
+        let mut skills = SkillManager::new();
+        let mut manager = DialogueManager::new(Recognizer::new(&engine));
+
+        manager.handle("s", "set a timer", &mut skills);
+        for _ in 0..DEFAULT_MAX_REPROMPTS {
+            assert_eq!(
+                manager.handle("s", "whenever", &mut skills),
+                vec!["ask_duration"]
+            );
+        }
+        assert_eq!(
+            manager.handle("s", "whenever", &mut skills),
+            vec!["Sorry, I didn't get that. Let's leave it for now."]
+        );
+        assert!(!manager.is_active("s"));
+    }
+
+    #[test]
+    fn sample_skill_asks_for_the_destination_it_books() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let folder = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("skills/my_skill");
+        skills.add_skill(&folder, &mut engine).unwrap();
+        engine.prepare();
+        let mut manager = DialogueManager::new(Recognizer::new(&engine));
+
+        assert!(
+            manager
+                .handle("s", "i want to fly to big apple", &mut skills)
+                .is_empty()
+        );
+        assert!(!manager.is_active("s"));
+
+        assert_eq!(
+            manager.handle("s", "book a flight", &mut skills),
+            vec!["Where would you like to fly to?"]
+        );
+        assert!(manager.handle("s", "nyc", &mut skills).is_empty());
+        assert!(!manager.is_active("s"));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod ask;
+pub(crate) mod expected;
+pub mod manager;
+mod session;
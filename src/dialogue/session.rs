@@ -0,0 +1,58 @@
+use crate::dialogue::ask::AskRequest;
+use crate::intent::slot_extrator::ExtractedSlots;
+use rhai::FnPtr;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// What the next utterance of a session answers.
+pub(crate) enum Pending {
+    /// A required slot of an intent that is not dispatched yet, asked with response `prompt`
+    Slot {
+        intent: ExtractedSlots,
+        slot: String,
+        prompt: String,
+    },
+    /// An `ask` function called by the handler of `intent`
+    Ask {
+        intent: ExtractedSlots,
+        request: AskRequest,
+    },
+}
+
+/// Conversation state of one user or device.
+pub(crate) struct Session {
+    pub(crate) pending: Option<Pending>,
+    /// Times the pending question was asked again
+    pub(crate) reprompts: u32,
+    /// Intents of the last utterance still waiting for their turn
+    pub(crate) queue: VecDeque<ExtractedSlots>,
+    /// `ask.cancel` handler of the running interaction and the intent that set it
+    pub(crate) cancel: Option<(ExtractedSlots, FnPtr)>,
+    /// The intent dispatched last, the context follow-up patterns continue
+    pub(crate) last_intent: Option<ExtractedSlots>,
+    pub(crate) last_activity: Instant,
+}
+
+impl Session {
+    pub(crate) fn new() -> Self {
+        Session {
+            pending: None,
+            reprompts: 0,
+            queue: VecDeque::new(),
+            cancel: None,
+            last_intent: None,
+            last_activity: Instant::now(),
+        }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Forgets the interaction, returning its cancel handler.
+    pub(crate) fn clear(&mut self) -> Option<(ExtractedSlots, FnPtr)> {
+        self.pending = None;
+        self.queue.clear();
+        self.cancel.take()
+    }
+}
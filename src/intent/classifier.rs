@@ -0,0 +1,123 @@
+use crate::intent::intent::Intent;
+use std::collections::HashMap;
+
+/// Threshold for intents that don't set `classifier_threshold`. Sharing a single common word
+/// with an example ("hello world" against "hello") stays below it.
+pub(crate) const DEFAULT_CLASSIFIER_THRESHOLD: f64 = 0.6;
+
+type Vector = HashMap<String, f64>;
+
+/// TF-IDF model over the example phrasings of every intent, used when no pattern matches.
+/// Features are words, word pairs and character trigrams, so a reworded or slightly
+/// misspelled utterance still lands close to the examples it resembles.
+pub(crate) struct Classifier {
+    idf: HashMap<String, f64>,
+    /// IDF given to features never seen in training
+    unseen_idf: f64,
+    /// Index of the intent and the normalized vector of each example
+    examples: Vec<(usize, Vector)>,
+}
+
+impl Classifier {
+    pub(crate) fn train(intents: &[Intent]) -> Self {
+        let documents: Vec<(usize, Vec<String>)> = intents
+            .iter()
+            .enumerate()
+            .flat_map(|(index, intent)| {
+                intent
+                    .examples
+                    .iter()
+                    .map(move |example| (index, features(example)))
+            })
+            .filter(|(_, features)| !features.is_empty())
+            .collect();
+
+        let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+        for (_, features) in &documents {
+            let mut seen: Vec<&str> = features.iter().map(String::as_str).collect();
+            seen.sort();
+            seen.dedup();
+            for feature in seen {
+                *document_frequency.entry(feature).or_default() += 1;
+            }
+        }
+
+        let count = documents.len() as f64;
+        let idf: HashMap<String, f64> = document_frequency
+            .into_iter()
+            .map(|(feature, df)| {
+                (
+                    feature.to_string(),
+                    ((1.0 + count) / (1.0 + df as f64)).ln() + 1.0,
+                )
+            })
+            .collect();
+        let unseen_idf = (1.0 + count).ln() + 1.0;
+
+        let mut classifier = Classifier {
+            idf,
+            unseen_idf,
+            examples: Vec::new(),
+        };
+        classifier.examples = documents
+            .iter()
+            .map(|(index, features)| (*index, classifier.vectorize(features)))
+            .collect();
+
+        classifier
+    }
+
+    fn vectorize(&self, features: &[String]) -> Vector {
+        let mut vector = Vector::new();
+        for feature in features {
+            let idf = self.idf.get(feature).copied().unwrap_or(self.unseen_idf);
+            *vector.entry(feature.clone()).or_default() += idf;
+        }
+
+        let norm = vector.values().map(|v| v * v).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            vector.values_mut().for_each(|v| *v /= norm);
+        }
+        vector
+    }
+
+    /// Cosine similarity of the text to the closest example of each intent, best first.
+    pub(crate) fn classify(&self, text: &str) -> Vec<(usize, f64)> {
+        let query = self.vectorize(&features(text));
+        let mut best: HashMap<usize, f64> = HashMap::new();
+
+        for (index, example) in &self.examples {
+            let similarity: f64 = query
+                .iter()
+                .filter_map(|(feature, weight)| example.get(feature).map(|w| w * weight))
+                .sum();
+
+            let entry = best.entry(*index).or_default();
+            *entry = entry.max(similarity);
+        }
+
+        let mut scores: Vec<(usize, f64)> = best.into_iter().collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores
+    }
+}
+
+fn features(text: &str) -> Vec<String> {
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    let mut features = Vec::new();
+
+    for word in &words {
+        features.push(format!("w:{}", word));
+
+        let chars: Vec<char> = format!("#{}#", word).chars().collect();
+        for trigram in chars.windows(3) {
+            features.push(format!("c:{}", trigram.iter().collect::<String>()));
+        }
+    }
+
+    for pair in words.windows(2) {
+        features.push(format!("b:{} {}", pair[0], pair[1]));
+    }
+
+    features
+}
@@ -0,0 +1,358 @@
+use crate::intent::engine::IntentEngine;
+use crate::intent::intent::Intent;
+use crate::intent::slot::SlotDefinition;
+use crate::intent::slot_extrator::SlotExtractor;
+use regex_syntax::ParserBuilder;
+use regex_syntax::hir::{Hir, HirKind};
+use std::fmt;
+
+/// A problem found in the loaded intents, see `IntentEngine::analyze`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// Both intents have the same pattern, the first one loaded always wins
+    IdenticalPattern {
+        intent: String,
+        other_intent: String,
+        pattern: String,
+    },
+    /// Everything `pattern` matches is also matched by `other_pattern`
+    SubsumedPattern {
+        intent: String,
+        pattern: String,
+        other_intent: String,
+        other_pattern: String,
+    },
+    /// A regex pattern that can never match an utterance
+    UnreachableRegex {
+        intent: String,
+        regex: String,
+        reason: String,
+    },
+    /// A `{slot}` with no entry in `slots`, the pattern never matches
+    UndefinedSlot {
+        intent: String,
+        pattern: String,
+        slot: String,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::IdenticalPattern {
+                intent,
+                other_intent,
+                pattern,
+            } => write!(
+                f,
+                "Intents {} and {} both have the pattern \"{}\"",
+                intent, other_intent, pattern
+            ),
+            Diagnostic::SubsumedPattern {
+                intent,
+                pattern,
+                other_intent,
+                other_pattern,
+            } => write!(
+                f,
+                "Pattern \"{}\" of {} is covered by \"{}\" of {}",
+                pattern, intent, other_pattern, other_intent
+            ),
+            Diagnostic::UnreachableRegex {
+                intent,
+                regex,
+                reason,
+            } => write!(
+                f,
+                "Regex pattern \"{}\" of {} can never match: {}",
+                regex, intent, reason
+            ),
+            Diagnostic::UndefinedSlot {
+                intent,
+                pattern,
+                slot,
+            } => write!(
+                f,
+                "Slot {{{}}} in pattern \"{}\" of {} is not defined in its slots",
+                slot, pattern, intent
+            ),
+        }
+    }
+}
+
+/// Text a slot can be filled with when checking whether a pattern covers another one.
+/// `None` when there is no obvious value, the pattern is then left out of that check.
+fn sample_value(engine: &IntentEngine, intent: &Intent, slot: &str) -> Option<String> {
+    let builtin = |name: &str| match name {
+        "number" => Some("2".to_string()),
+        "ordinal" => Some("first".to_string()),
+        "percentage" => Some("50%".to_string()),
+        "duration" => Some("2 hours".to_string()),
+        "time" => Some("5pm".to_string()),
+        "date" | "dates" => Some("tomorrow".to_string()),
+        _ => None,
+    };
+
+    let defn = if let Some(name) = slot.strip_prefix("default/") {
+        engine.default_slots.get(name).map(|d| (d, builtin(name)))
+    } else if let Some(name) = slot.strip_prefix("entity/") {
+        engine.entities.get(name).map(|d| (d, None))
+    } else {
+        intent.slots.get(slot).map(|d| (d, None))
+    };
+
+    match defn? {
+        (SlotDefinition::Enumeration { values }, _) => values.first().cloned(),
+        (SlotDefinition::Entity { synonyms, .. }, _) => synonyms.keys().min().cloned(),
+        (SlotDefinition::CatchAll, _) => Some("something".to_string()),
+        (_, sample) => sample,
+    }
+}
+
+/// Concrete utterances for every phrasing of a pattern, `None` if a slot has no sample.
+fn samples(engine: &IntentEngine, intent: &Intent, pattern_index: usize) -> Option<Vec<String>> {
+    intent.variants[pattern_index]
+        .iter()
+        .map(|variant| {
+            variant
+                .split_whitespace()
+                .map(
+                    |word| match word.strip_prefix('{').and_then(|w| w.strip_suffix('}')) {
+                        Some(slot) => sample_value(engine, intent, slot),
+                        None if word == "*" => Some("something".to_string()),
+                        None => Some(word.to_string()),
+                    },
+                )
+                .collect::<Option<Vec<_>>>()
+                .map(|words| words.join(" "))
+        })
+        .collect()
+}
+
+fn same_language(a: &Intent, b: &Intent) -> bool {
+    match (&a.language, &b.language) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+/// Characters a match of `hir` always contains.
+fn required_chars(hir: &Hir, chars: &mut Vec<char>) {
+    match hir.kind() {
+        HirKind::Literal(literal) => chars.extend(String::from_utf8_lossy(&literal.0).chars()),
+        HirKind::Capture(capture) => required_chars(&capture.sub, chars),
+        HirKind::Repetition(repetition) if repetition.min > 0 => {
+            required_chars(&repetition.sub, chars)
+        }
+        HirKind::Concat(subs) => subs.iter().for_each(|sub| required_chars(sub, chars)),
+        _ => {}
+    }
+}
+
+fn unreachable_reason(engine: &IntentEngine, intent: &Intent, regex: &str) -> Option<String> {
+    let hir = ParserBuilder::new()
+        .case_insensitive(true)
+        .build()
+        .parse(regex)
+        .ok()?;
+
+    if hir.properties().minimum_len().is_none() {
+        return Some("no text satisfies it".to_string());
+    }
+
+    if intent.normalize {
+        let mut chars = Vec::new();
+        required_chars(&hir, &mut chars);
+        if let Some(c) = chars.iter().find(|c| engine.normalizer.always_strips(**c)) {
+            return Some(format!(
+                "it requires '{}', which normalization removes (set \"normalize\": false)",
+                c
+            ));
+        }
+    }
+
+    None
+}
+
+pub(crate) fn analyze(engine: &IntentEngine) -> Vec<Diagnostic> {
+    let intents = &engine.intents;
+    let mut diagnostics = Vec::new();
+
+    let extractor = SlotExtractor::new(&engine.default_slots, &engine.entities);
+
+    // Overlapping patterns between different intents
+    for (a_index, a) in intents.iter().enumerate() {
+        for (b_index, b) in intents.iter().enumerate() {
+            if a.name == b.name || !same_language(a, b) {
+                continue;
+            }
+
+            for (i, pattern) in a.patterns.iter().enumerate() {
+                for (j, other_pattern) in b.patterns.iter().enumerate() {
+                    if pattern == other_pattern {
+                        if a_index < b_index {
+                            diagnostics.push(Diagnostic::IdenticalPattern {
+                                intent: a.name.clone(),
+                                other_intent: b.name.clone(),
+                                pattern: pattern.clone(),
+                            });
+                        }
+                        continue;
+                    }
+
+                    // Covered when the other pattern takes every sample, slots and all: matching
+                    // the text is not enough if its slot processors turn the value down
+                    let covered = samples(engine, a, i).is_some_and(|samples| {
+                        samples.iter().all(|sample| {
+                            extractor
+                                .extract_from_pattern(
+                                    &b.compiled_patterns[j],
+                                    sample,
+                                    &b.name,
+                                    &b.slots,
+                                )
+                                .is_some()
+                        })
+                    });
+                    if covered {
+                        diagnostics.push(Diagnostic::SubsumedPattern {
+                            intent: a.name.clone(),
+                            pattern: pattern.clone(),
+                            other_intent: b.name.clone(),
+                            other_pattern: other_pattern.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    for intent in intents {
+        for regex in &intent.compiled_regex_patterns {
+            if let Some(reason) = unreachable_reason(engine, intent, regex.as_str()) {
+                diagnostics.push(Diagnostic::UnreachableRegex {
+                    intent: intent.name.clone(),
+                    regex: regex.as_str().to_string(),
+                    reason,
+                });
+            }
+        }
+
+        for (pattern, regex) in intent.patterns.iter().zip(&intent.compiled_patterns) {
+            for slot in regex.capture_names().flatten() {
+                if !slot.starts_with("default_")
+                    && !slot.starts_with("entity_")
+                    && !intent.slots.contains_key(slot)
+                {
+                    diagnostics.push(Diagnostic::UndefinedSlot {
+                        intent: intent.name.clone(),
+                        pattern: pattern.clone(),
+                        slot: slot.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // An intent loaded once per language reports the same overlaps
+    let mut unique = Vec::new();
+    for diagnostic in diagnostics {
+        if !unique.contains(&diagnostic) {
+            unique.push(diagnostic);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intent::engine::tests::{load, load_for};
+
+    #[test]
+    fn covered_patterns_take_slot_processors_into_account() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "music", "patterns": ["play {song}"], "slots": {"song": "*"}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "track", "patterns": ["play {default/number}"]}"#,
+        );
+
+        // Any song name is not a number, but every number is a song name
+        assert_eq!(
+            engine.analyze(),
+            vec![Diagnostic::SubsumedPattern {
+                intent: "track".to_string(),
+                pattern: "play {default/number}".to_string(),
+                other_intent: "music".to_string(),
+                other_pattern: "play {song}".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn identical_patterns_are_reported_once() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "stop", "patterns": ["stop it"]}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "pause", "patterns": ["stop it"]}"#,
+        );
+
+        assert_eq!(
+            engine.analyze(),
+            vec![Diagnostic::IdenticalPattern {
+                intent: "stop".to_string(),
+                other_intent: "pause".to_string(),
+                pattern: "stop it".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn regexes_that_can_never_match_are_reported() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "greet", "regex_patterns": ["hello, world", "[a&&b]"]}"#,
+        );
+
+        let reasons: Vec<String> = engine
+            .analyze()
+            .into_iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        assert_eq!(reasons.len(), 2);
+        assert!(reasons[0].contains("requires ','"));
+        assert!(reasons[1].contains("no text satisfies it"));
+    }
+
+    #[test]
+    fn only_one_skill_defines_an_intent() {
+        let mut engine = IntentEngine::new();
+        load(&mut engine, r#"{"intent": "greet", "patterns": ["hi"]}"#);
+
+        assert!(
+            load_for(
+                &mut engine,
+                "other.skill",
+                r#"{"intent": "greet", "patterns": ["hello"]}"#
+            )
+            .is_err()
+        );
+        assert!(
+            load_for(
+                &mut engine,
+                "test.skill",
+                r#"{"intent": "greet", "patterns": ["hello"]}"#
+            )
+            .is_ok()
+        );
+    }
+}
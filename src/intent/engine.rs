@@ -1,14 +1,56 @@
-use crate::intent::intent::{Intent, IntentFile};
+use crate::intent::classifier::{Classifier, DEFAULT_CLASSIFIER_THRESHOLD};
+use crate::intent::diagnostics::{self, Diagnostic};
+use crate::intent::intent::{EntityFile, Intent, IntentFile, LocalizedPatterns};
+use crate::intent::normalizer::{Normalizer, NormalizerConfig};
+use crate::intent::prefilter::Prefilter;
+use crate::intent::processor::ProcessorLoader;
 use crate::intent::slot::{DefaultSlotManager, SlotDefinition};
+use crate::intent::slot_extrator::SlotExtractor;
+use crate::intent::template;
 use serde_json;
+use std::cell::OnceCell;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
+use std::rc::Rc;
+
+/// What loading an intent file needs to know about the skill it belongs to.
+pub(crate) struct SkillContext<'a> {
+    pub(crate) skill_id: &'a str,
+    /// The `languages` of the skill metadata
+    pub(crate) languages: &'a [String],
+    /// Resolves the AviScript slot processors of the skill
+    pub(crate) processors: &'a ProcessorLoader,
+}
+
+/// An entity taken out of the engine with `remove_skill_entities`.
+pub(crate) struct SkillEntity {
+    name: String,
+    skill_id: String,
+    file: EntityFile,
+    definition: SlotDefinition,
+}
 
 pub struct IntentEngine {
     pub(crate) intents: Vec<Intent>,
     pub(crate) default_slots: DefaultSlotManager,
+    /// Skill-defined slot types, referenced as `{entity/name}`
+    pub(crate) entities: HashMap<String, SlotDefinition>,
+    /// The entity files behind `entities`, kept to build them again, with the id of the
+    /// skill each was loaded from
+    entity_files: HashMap<String, (String, EntityFile)>,
+    pub(crate) normalizer: Normalizer,
+    /// Language utterances are recognized in, intents of other languages are skipped
+    pub(crate) language: String,
+    /// Minimum similarity for the fallback classifier, for intents that don't set their own
+    pub(crate) classifier_threshold: f64,
+    /// Words multi-intent utterances are split on, per language
+    pub(crate) conjunctions: HashMap<String, Vec<String>>,
+    /// Built on first recognition and dropped whenever the set of intents changes
+    prefilter: OnceCell<Prefilter>,
+    /// Trained on the intent examples, reset together with the prefilter
+    classifier: OnceCell<Classifier>,
 }
 
 impl IntentEngine {
@@ -16,36 +58,507 @@ impl IntentEngine {
         IntentEngine {
             intents: Vec::new(),
             default_slots: DefaultSlotManager::new(),
+            entities: HashMap::new(),
+            entity_files: HashMap::new(),
+            normalizer: Normalizer::new(NormalizerConfig::default()),
+            language: NormalizerConfig::default().language,
+            classifier_threshold: DEFAULT_CLASSIFIER_THRESHOLD,
+            conjunctions: default_conjunctions(),
+            prefilter: OnceCell::new(),
+            classifier: OnceCell::new(),
+        }
+    }
+
+    pub(crate) fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Switches the recognition language, loaded skills keep all their languages. Shared
+    /// patterns and entity values are normalized again for the new language. A
+    /// `DialogueManager` takes the language when it is created, it borrows the engine so
+    /// one is created again after switching.
+    pub fn set_language(&mut self, language: &str) {
+        if self.language == language {
+            return;
+        }
+        self.language = language.to_string();
+        self.normalizer = self.normalizer.with_language(language);
+        self.recompile();
+    }
+
+    /// Replaces the normalization stages and normalizes the loaded patterns and entity values
+    /// again. The language of the engine is kept.
+    pub(crate) fn set_normalizer_config(&mut self, config: NormalizerConfig) {
+        self.normalizer = Normalizer::new(config).with_language(&self.language);
+        self.recompile();
+    }
+
+    /// Builds the loaded entities and intents again from their files, with the current
+    /// normalizer. One that no longer compiles is reported and kept as it was.
+    fn recompile(&mut self) {
+        for (name, (_, data)) in &self.entity_files {
+            match self.build_entity(data) {
+                Ok(entity) => {
+                    self.entities.insert(name.clone(), entity);
+                }
+                Err(err) => eprintln!("Failed to rebuild entity {}: {}", name, err),
+            }
+        }
+
+        let intents = std::mem::take(&mut self.intents);
+        self.intents = intents
+            .into_iter()
+            .map(|intent| {
+                let source = Rc::clone(&intent.source);
+                self.compile_intent(
+                    &source,
+                    intent.language.as_deref(),
+                    &intent.skill_id,
+                    intent.slots.clone(),
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("Failed to recompile intent {}: {}", intent.name, err);
+                    intent
+                })
+            })
+            .collect();
+        self.prefilter.take();
+        self.classifier.take();
+    }
+
+    /// Replaces the words multi-intent utterances of a language are split on.
+    pub(crate) fn set_conjunctions(&mut self, language: &str, conjunctions: Vec<String>) {
+        self.conjunctions.insert(language.to_string(), conjunctions);
+    }
+
+    pub(crate) fn set_classifier_threshold(&mut self, threshold: f64) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(format!(
+                "Invalid classifier_threshold: {} (expected 0.0 to 1.0)",
+                threshold
+            ));
         }
+        self.classifier_threshold = threshold;
+        Ok(())
+    }
+
+    pub(crate) fn prefilter(&self) -> &Prefilter {
+        self.prefilter
+            .get_or_init(|| Prefilter::build(&self.intents))
+    }
+
+    pub(crate) fn classifier(&self) -> &Classifier {
+        self.classifier
+            .get_or_init(|| Classifier::train(&self.intents))
+    }
+
+    /// Builds the prefilter and trains the classifier now instead of on the first utterance.
+    pub(crate) fn prepare(&self) {
+        self.prefilter();
+        self.classifier();
+    }
+
+    /// Looks for conflicts between the loaded intents: overlapping patterns, regexes that
+    /// can't match and undefined slots.
+    pub(crate) fn analyze(&self) -> Vec<Diagnostic> {
+        diagnostics::analyze(self)
     }
 
+    /// Loads an intent file and returns the intent name. `language` is set for files under
+    /// `intents/<lang>/`. A file with per-language patterns becomes one intent per language.
     pub(crate) fn load_intent<P: AsRef<Path>>(
         &mut self,
         file_path: P,
+        language: Option<&str>,
+        skill: &SkillContext,
     ) -> Result<String, Box<dyn Error>> {
         let content = fs::read_to_string(file_path)?;
-        let data: IntentFile = serde_json::from_str(&content)?;
+        self.load_intent_from_str(&content, language, skill)
+    }
+
+    /// Same as `load_intent` for the content of an intent file.
+    pub(crate) fn load_intent_from_str(
+        &mut self,
+        content: &str,
+        language: Option<&str>,
+        skill: &SkillContext,
+    ) -> Result<String, Box<dyn Error>> {
+        let data: Rc<IntentFile> = Rc::new(serde_json::from_str(content)?);
 
         // Check if the intent has at least one pattern or regex pattern
         if !data.is_valid() {
             return Err("Intent must have at least one pattern or regex pattern".into());
         }
 
-        let slots = self.parse_slot_defs(&data.slots).unwrap_or_default();
-        let intent = Intent {
-            name: data.intent,
-            patterns: data.patterns,
-            regex_patterns: data.regex_patterns,
-            slots,
+        // Only one skill can handle an intent
+        if let Some(other) = self
+            .intents
+            .iter()
+            .find(|i| i.name == data.intent && i.skill_id != skill.skill_id)
+        {
+            return Err(format!(
+                "Intent {} is already defined by skill {}",
+                data.intent, other.skill_id
+            )
+            .into());
+        }
+
+        for (setting, value) in [
+            ("fuzzy_threshold", data.fuzzy_threshold),
+            ("classifier_threshold", data.classifier_threshold),
+        ] {
+            if let Some(threshold) = value
+                && !(0.0..=1.0).contains(&threshold)
+            {
+                return Err(format!(
+                    "Invalid {} for {}: {} (expected 0.0 to 1.0)",
+                    setting, data.intent, threshold
+                )
+                .into());
+            }
+        }
+
+        let slots = self
+            .parse_slot_defs(&data.slots, skill.processors)
+            .map_err(|err| format!("Invalid slots in {}: {}", data.intent, err))?;
+
+        let mut languages: Vec<Option<&str>> = data
+            .patterns
+            .languages()
+            .into_iter()
+            .chain(data.regex_patterns.languages())
+            .map(|l| Some(l.as_str()))
+            .collect();
+        languages.sort();
+        languages.dedup();
+        if languages.is_empty() {
+            languages.push(language);
+        }
+
+        let mut loaded = Vec::new();
+        for language in languages {
+            if let Some(language) = language
+                && !skill.languages.iter().any(|s| s == language)
+            {
+                eprintln!(
+                    "Skipping {} patterns of {}, the skill does not list that language",
+                    language, data.intent
+                );
+                continue;
+            }
+
+            let intent = self.compile_intent(&data, language, skill.skill_id, slots.clone())?;
+            if !intent.compiled_patterns.is_empty() || !intent.compiled_regex_patterns.is_empty() {
+                loaded.push(intent);
+            }
+        }
+
+        if loaded.is_empty() {
+            return Err(format!(
+                "Intent {} has no patterns in a supported language",
+                data.intent
+            )
+            .into());
+        }
+
+        self.intents.extend(loaded);
+        self.prefilter.take();
+        self.classifier.take();
+        Ok(data.intent.clone())
+    }
+
+    /// Names of the intents of a skill, once for all its languages.
+    pub(crate) fn skill_intents(&self, skill_id: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .intents
+            .iter()
+            .filter(|intent| intent.skill_id == skill_id)
+            .map(|intent| intent.name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Unloads the intents of a skill, in every language, and returns them.
+    pub(crate) fn remove_skill_intents(&mut self, skill_id: &str) -> Vec<Intent> {
+        let (removed, kept) = self
+            .intents
+            .drain(..)
+            .partition(|intent| intent.skill_id == skill_id);
+        self.intents = kept;
+        self.prefilter.take();
+        self.classifier.take();
+        removed
+    }
+
+    /// Number of loaded intents. The intents loaded after it are unloaded with
+    /// `remove_intents_since`.
+    pub(crate) fn intent_count(&self) -> usize {
+        self.intents.len()
+    }
+
+    /// Unloads the intents loaded since the engine had `count` of them.
+    pub(crate) fn remove_intents_since(&mut self, count: usize) -> Vec<Intent> {
+        let removed = self.intents.split_off(count.min(self.intents.len()));
+        self.prefilter.take();
+        self.classifier.take();
+        removed
+    }
+
+    /// Puts back intents taken out with `remove_skill_intents`.
+    pub(crate) fn restore_intents(&mut self, intents: Vec<Intent>) {
+        self.intents.extend(intents);
+        self.prefilter.take();
+        self.classifier.take();
+    }
+
+    fn compile_intent(
+        &self,
+        data: &Rc<IntentFile>,
+        language: Option<&str>,
+        skill_id: &str,
+        slots: HashMap<String, SlotDefinition>,
+    ) -> Result<Intent, Box<dyn Error>> {
+        let patterns = data.patterns.for_language(language);
+        let regex_patterns = data.regex_patterns.for_language(language);
+
+        // Patterns go through the same normalization as the utterances they are matched against
+        let normalizer = match language {
+            Some(language) => self.normalizer.with_language(language),
+            None => self.normalizer.clone(),
+        };
+        let prepare = |text: &String| {
+            if data.normalize {
+                normalizer.normalize_pattern(text)
+            } else {
+                text.clone()
+            }
         };
-        let name = intent.name.clone();
-        self.intents.push(intent);
+        let patterns: Vec<String> = patterns.iter().map(prepare).collect();
+
+        let extractor = SlotExtractor::new(&self.default_slots, &self.entities);
+        let mut compiled_patterns = Vec::with_capacity(patterns.len());
+        for pattern in &patterns {
+            let regex = extractor.pattern_to_regex(pattern).map_err(|err| {
+                format!(
+                    "Invalid pattern \"{}\" in {}: {}",
+                    pattern, data.intent, err
+                )
+            })?;
+            compiled_patterns.push(regex);
+        }
+
+        let mut compiled_follow_ups = Vec::new();
+        for pattern in data.follow_up_patterns.for_language(language) {
+            let pattern = prepare(&pattern);
+            let regex = extractor.pattern_to_regex(&pattern).map_err(|err| {
+                format!(
+                    "Invalid follow-up pattern \"{}\" in {}: {}",
+                    pattern, data.intent, err
+                )
+            })?;
+            compiled_follow_ups.push(regex);
+        }
+
+        let follows = if data.follows.is_empty() {
+            vec![data.intent.clone()]
+        } else {
+            data.follows.clone()
+        };
+
+        let variants = patterns
+            .iter()
+            .map(|p| template::parse(p).map(|nodes| template::expand(&nodes)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let examples = variants
+            .iter()
+            .flatten()
+            .map(|variant| {
+                variant
+                    .split_whitespace()
+                    .filter(|word| !word.starts_with('{') && *word != "*")
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|example| !example.is_empty())
+            .collect();
+
+        let mut exclude_patterns = Vec::new();
+        for pattern in data.exclude_patterns.for_language(language) {
+            let pattern = prepare(&pattern);
+            let regex = template::parse(&pattern)
+                .and_then(|nodes| template::to_regex(&nodes, &|_| Ok(".+?".to_string())))
+                .and_then(|body| {
+                    Ok(SlotExtractor::compile_regex(&format!(
+                        "(?:^|\\s)(?:{})(?:\\s|$)",
+                        body
+                    ))?)
+                })
+                .map_err(|err| {
+                    format!(
+                        "Invalid exclude pattern \"{}\" in {}: {}",
+                        pattern, data.intent, err
+                    )
+                })?;
+            exclude_patterns.push(regex);
+        }
+
+        let keywords = |list: &LocalizedPatterns| -> Vec<String> {
+            list.for_language(language)
+                .iter()
+                .map(|keyword| prepare(keyword).to_lowercase())
+                .collect()
+        };
+
+        let mut compiled_regex_patterns = Vec::with_capacity(regex_patterns.len());
+        for regex_pattern in &regex_patterns {
+            let regex = SlotExtractor::compile_regex(regex_pattern).map_err(|err| {
+                format!(
+                    "Invalid regex pattern \"{}\" in {}: {}",
+                    regex_pattern, data.intent, err
+                )
+            })?;
+            compiled_regex_patterns.push(regex);
+        }
+
+        Ok(Intent {
+            name: data.intent.clone(),
+            skill_id: skill_id.to_string(),
+            language: language.map(str::to_string),
+            compiled_patterns,
+            compiled_regex_patterns,
+            patterns,
+            variants,
+            examples,
+            exclude_patterns,
+            compiled_follow_ups,
+            follows,
+            required_keywords: keywords(&data.required_keywords),
+            forbidden_keywords: keywords(&data.forbidden_keywords),
+            slots,
+            required_slots: data.required_slots.clone(),
+            fuzzy_threshold: data.fuzzy_threshold,
+            classifier_threshold: data.classifier_threshold,
+            normalize: data.normalize,
+            source: Rc::clone(data),
+        })
+    }
+
+    /// Loads an entity file of a skill, which has to happen before the intents that use it.
+    pub(crate) fn load_entity<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+        skill_id: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let content = fs::read_to_string(file_path)?;
+        let data: EntityFile = serde_json::from_str(&content)?;
+
+        if !data.is_valid() {
+            return Err("Entity must have at least one value or validator".into());
+        }
+
+        if let Some((owner, _)) = self.entity_files.get(&data.entity)
+            && owner != skill_id
+        {
+            return Err(format!(
+                "Entity {} is already defined by skill {}",
+                data.entity, owner
+            )
+            .into());
+        }
+
+        let entity = self.build_entity(&data)?;
+        if self.entities.contains_key(&data.entity) {
+            eprintln!("Entity {} was already defined, replacing it", data.entity);
+        }
+
+        let name = data.entity.clone();
+        self.entities.insert(name.clone(), entity);
+        self.entity_files
+            .insert(name.clone(), (skill_id.to_string(), data));
         Ok(name)
     }
 
+    /// Unloads the entities of a skill and returns them.
+    pub(crate) fn remove_skill_entities(&mut self, skill_id: &str) -> Vec<SkillEntity> {
+        let names: Vec<String> = self
+            .entity_files
+            .iter()
+            .filter(|(_, (owner, _))| owner == skill_id)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let (skill_id, file) = self.entity_files.remove(&name)?;
+                let definition = self.entities.remove(&name)?;
+                Some(SkillEntity {
+                    name,
+                    skill_id,
+                    file,
+                    definition,
+                })
+            })
+            .collect()
+    }
+
+    /// Unloads the named entities of a skill, leaving alone the ones another skill defines.
+    pub(crate) fn remove_entities(&mut self, skill_id: &str, names: &[String]) {
+        for name in names {
+            if self
+                .entity_files
+                .get(name)
+                .is_some_and(|(owner, _)| owner == skill_id)
+            {
+                self.entity_files.remove(name);
+                self.entities.remove(name);
+            }
+        }
+    }
+
+    /// Puts back entities taken out with `remove_skill_entities`.
+    pub(crate) fn restore_entities(&mut self, entities: Vec<SkillEntity>) {
+        for entity in entities {
+            self.entities.insert(entity.name.clone(), entity.definition);
+            self.entity_files
+                .insert(entity.name, (entity.skill_id, entity.file));
+        }
+    }
+
+    fn build_entity(&self, data: &EntityFile) -> Result<SlotDefinition, Box<dyn Error>> {
+        // Values are looked up both as written and normalized, so they match whichever form
+        // the intent recognizes against
+        let mut synonyms = HashMap::new();
+        for value in &data.values {
+            for synonym in value.synonyms.iter().chain([&value.value]) {
+                for form in [synonym.clone(), self.normalizer.normalize(synonym)] {
+                    synonyms.insert(form.to_lowercase(), value.value.clone());
+                }
+            }
+        }
+
+        let mut validators = Vec::with_capacity(data.validators.len());
+        for validator in &data.validators {
+            let regex =
+                SlotExtractor::compile_regex(&format!("^(?:{})$", validator)).map_err(|err| {
+                    format!(
+                        "Invalid validator \"{}\" in entity {}: {}",
+                        validator, data.entity, err
+                    )
+                })?;
+            validators.push(regex);
+        }
+
+        Ok(SlotDefinition::new_entity(synonyms, validators))
+    }
+
     fn parse_slot_defs(
         &self,
         raw_slots: &HashMap<String, serde_json::Value>,
+        processors: &ProcessorLoader,
     ) -> Result<HashMap<String, SlotDefinition>, Box<dyn Error>> {
         let mut defs = HashMap::new();
         for (slot, val) in raw_slots {
@@ -54,6 +567,10 @@ impl IntentEngine {
             } else if val.is_array() {
                 let values: Vec<String> = serde_json::from_value(val.clone())?;
                 defs.insert(slot.clone(), SlotDefinition::new_enumeration(values));
+            } else if let Some(processor) = val.get("processor").and_then(|p| p.as_str()) {
+                // "processor": "module::function", an AviScript function of the skill
+                let processor = processors.load(processor)?;
+                defs.insert(slot.clone(), SlotDefinition::new_script(processor));
             } else {
                 return Err(format!("Invalid slot definition for {}: {:?}", slot, val).into());
             }
@@ -62,3 +579,203 @@ impl IntentEngine {
         Ok(defs)
     }
 }
+
+fn default_conjunctions() -> HashMap<String, Vec<String>> {
+    let mut conjunctions = HashMap::new();
+    conjunctions.insert(
+        "en".to_string(),
+        vec![
+            "and".to_string(),
+            "and then".to_string(),
+            "then".to_string(),
+        ],
+    );
+    conjunctions.insert(
+        "pt".to_string(),
+        vec![
+            "e".to_string(),
+            "e depois".to_string(),
+            "depois".to_string(),
+        ],
+    );
+    conjunctions
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::intent::recognizer::Recognizer;
+    use rhai::Engine;
+    use std::path::PathBuf;
+
+    pub(crate) fn load(engine: &mut IntentEngine, content: &str) {
+        load_for(engine, "test.skill", content).unwrap();
+    }
+
+    pub(crate) fn load_for(
+        engine: &mut IntentEngine,
+        skill_id: &str,
+        content: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let processors = ProcessorLoader::new(Rc::new(Engine::new_raw()), PathBuf::new());
+        let context = SkillContext {
+            skill_id,
+            languages: &["en".to_string(), "pt".to_string()],
+            processors: &processors,
+        };
+        engine.load_intent_from_str(content, None, &context)
+    }
+
+    pub(crate) fn matches(engine: &IntentEngine, text: &str) -> Vec<String> {
+        Recognizer::new(engine)
+            .recognize_with(text, false)
+            .into_iter()
+            .map(|m| m.intent)
+            .collect()
+    }
+
+    fn load_entity(
+        engine: &mut IntentEngine,
+        skill_id: &str,
+        content: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("avi-test-{}.entity", skill_id));
+        fs::write(&path, content).unwrap();
+        engine.load_entity(&path, skill_id)
+    }
+
+    #[test]
+    fn entities_resolve_synonyms_and_validators() {
+        let mut engine = IntentEngine::new();
+        load_entity(
+            &mut engine,
+            "test.skill",
+            r#"{"entity": "city", "values": [{"value": "new york", "synonyms": ["nyc", "big apple", "new york city"]}, {"value": "paris"}]}"#,
+        )
+        .unwrap();
+        load_entity(
+            &mut engine,
+            "test.skill",
+            r#"{"entity": "flight_code", "validators": ["[a-z]{2}\\d{3}"]}"#,
+        )
+        .unwrap();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "patterns": ["fly to {entity/city}"]}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "track", "patterns": ["track {entity/flight_code}"]}"#,
+        );
+        let recognizer = Recognizer::new(&engine);
+        let slot = |text: &str, slot: &str| {
+            recognizer
+                .recognize_with(text, false)
+                .first()
+                .map(|found| found.slots[slot].value.clone())
+        };
+
+        assert_eq!(
+            slot("fly to Big Apple", "entity_city").as_deref(),
+            Some("new york")
+        );
+        assert_eq!(
+            slot("fly to new york city", "entity_city").as_deref(),
+            Some("new york")
+        );
+        assert_eq!(
+            slot("fly to paris", "entity_city").as_deref(),
+            Some("paris")
+        );
+        assert_eq!(slot("fly to atlantis", "entity_city"), None);
+        assert_eq!(
+            slot("track tp123", "entity_flight_code").as_deref(),
+            Some("tp123")
+        );
+        assert_eq!(slot("track my flight", "entity_flight_code"), None);
+
+        // Another skill can't take the name
+        assert!(
+            load_entity(
+                &mut engine,
+                "other.skill",
+                r#"{"entity": "city", "values": [{"value": "lisbon"}]}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn changing_the_normalizer_renormalizes_loaded_patterns() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "coffee", "patterns": ["um café"]}"#,
+        );
+        assert!(matches(&engine, "um cafe").is_empty());
+
+        let mut config = engine.normalizer.config().clone();
+        config.set_stage("fold_accents", true).unwrap();
+        engine.set_normalizer_config(config);
+
+        assert_eq!(matches(&engine, "um cafe"), vec!["coffee"]);
+        assert_eq!(matches(&engine, "um café"), vec!["coffee"]);
+        assert_eq!(engine.language(), "en");
+    }
+
+    #[test]
+    fn only_the_patterns_of_the_current_language_match() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "greet", "patterns": {"en": ["good morning"], "pt": ["bom dia"], "fr": ["bonjour"]}}"#,
+        );
+        load(&mut engine, r#"{"intent": "stop", "patterns": ["stop"]}"#);
+
+        assert_eq!(matches(&engine, "good morning"), vec!["greet"]);
+        assert!(matches(&engine, "bom dia").is_empty());
+
+        engine.set_language("pt");
+        assert!(matches(&engine, "good morning").is_empty());
+        assert_eq!(matches(&engine, "stop"), vec!["stop"]);
+        let greeting = Recognizer::new(&engine).recognize_best("bom dia").unwrap();
+        assert_eq!(
+            (greeting.intent.as_str(), greeting.language.as_str()),
+            ("greet", "pt")
+        );
+
+        // The skill does not list French, so its patterns were never loaded
+        engine.set_language("fr");
+        assert!(matches(&engine, "bonjour").is_empty());
+    }
+
+    #[test]
+    fn changing_the_language_renormalizes_shared_patterns() {
+        let mut engine = IntentEngine::new();
+        let mut config = engine.normalizer.config().clone();
+        config.set_stage("numbers_to_digits", true).unwrap();
+        engine.set_normalizer_config(config);
+        load(
+            &mut engine,
+            r#"{"intent": "temperature", "patterns": ["vinte e um graus"]}"#,
+        );
+        assert!(matches(&engine, "21 graus").is_empty());
+
+        engine.set_language("pt");
+        assert_eq!(matches(&engine, "21 graus"), vec!["temperature"]);
+        assert_eq!(matches(&engine, "vinte e um graus"), vec!["temperature"]);
+    }
+
+    #[test]
+    fn skill_intents_are_removed_and_restored() {
+        let mut engine = IntentEngine::new();
+        load(&mut engine, r#"{"intent": "greet", "patterns": ["hello"]}"#);
+
+        assert_eq!(engine.skill_intents("test.skill"), vec!["greet"]);
+        let removed = engine.remove_skill_intents("test.skill");
+        assert!(matches(&engine, "hello").is_empty());
+
+        engine.restore_intents(removed);
+        assert_eq!(matches(&engine, "hello"), vec!["greet"]);
+    }
+}
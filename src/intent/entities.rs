@@ -0,0 +1,813 @@
+use crate::intent::normalizer::number_run;
+use chrono::{
+    Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday,
+};
+use rhai::{Dynamic, Map};
+
+/// Built-in entities understand both supported languages at once, so a slot keeps working
+/// whatever language the utterance was in.
+const LANGUAGES: [&str; 2] = ["en", "pt"];
+
+const EN_ORDINALS: [&str; 12] = [
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+    "eleventh", "twelfth",
+];
+
+const PT_ORDINALS: [&str; 10] = [
+    "primeir", "segund", "terceir", "quart", "quint", "sext", "sétim", "oitav", "non", "décim",
+];
+
+const MONTHS: [(&str, u32); 25] = [
+    ("january", 1),
+    ("february", 2),
+    ("march", 3),
+    ("april", 4),
+    ("may", 5),
+    ("june", 6),
+    ("july", 7),
+    ("august", 8),
+    ("september", 9),
+    ("october", 10),
+    ("november", 11),
+    ("december", 12),
+    ("janeiro", 1),
+    ("fevereiro", 2),
+    ("março", 3),
+    ("marco", 3),
+    ("abril", 4),
+    ("maio", 5),
+    ("junho", 6),
+    ("julho", 7),
+    ("agosto", 8),
+    ("setembro", 9),
+    ("outubro", 10),
+    ("novembro", 11),
+    ("dezembro", 12),
+];
+
+const WEEKDAYS: [(&str, Weekday); 16] = [
+    ("monday", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+    ("segunda", Weekday::Mon),
+    ("terça", Weekday::Tue),
+    ("terca", Weekday::Tue),
+    ("quarta", Weekday::Wed),
+    ("quinta", Weekday::Thu),
+    ("sexta", Weekday::Fri),
+    ("sábado", Weekday::Sat),
+    ("sabado", Weekday::Sat),
+    ("domingo", Weekday::Sun),
+];
+
+/// Years, months, weeks, days, hours, minutes and seconds.
+type DurationParts = [i64; 7];
+
+fn lower_words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// A whole number written with digits or words ("42", "forty two", "quarenta e dois").
+fn integer_value(words: &[String]) -> Option<i64> {
+    if let [word] = words
+        && let Ok(value) = word.parse::<i64>()
+    {
+        return Some(value);
+    }
+
+    LANGUAGES
+        .iter()
+        .find_map(|lang| match number_run(words, 0, lang) {
+            Some((value, end)) if end == words.len() => Some(value),
+            _ => None,
+        })
+}
+
+fn number_value(text: &str) -> Option<String> {
+    let mut words = lower_words(text);
+    let negative = words
+        .first()
+        .is_some_and(|w| matches!(w.as_str(), "minus" | "negative" | "menos"));
+    if negative {
+        words.remove(0);
+    }
+    let sign = if negative { -1 } else { 1 };
+
+    if let Some(value) = integer_value(&words) {
+        return value.checked_mul(sign).map(|value| value.to_string());
+    }
+
+    match words.as_slice() {
+        [word] => word
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(|value| (sign as f64 * value).to_string()),
+        _ => None,
+    }
+}
+
+fn ordinal_value(text: &str) -> Option<i64> {
+    let words = lower_words(text);
+    let (last, rest) = words.split_last()?;
+
+    if rest.is_empty() {
+        let digits = last.trim_end_matches(['.', 'º', 'ª']);
+        let digits = ["st", "nd", "rd", "th", "o", "a"]
+            .iter()
+            .find_map(|suffix| digits.strip_suffix(suffix))
+            .unwrap_or(digits);
+        if let Ok(value) = digits.parse::<i64>() {
+            return Some(value);
+        }
+    }
+
+    let unit = EN_ORDINALS
+        .iter()
+        .position(|o| o == last)
+        .or_else(|| {
+            PT_ORDINALS.iter().position(|stem| {
+                last.strip_prefix(stem)
+                    .is_some_and(|ending| matches!(ending, "o" | "a" | "os" | "as"))
+            })
+        })
+        .map(|index| index as i64 + 1)?;
+
+    // "twenty first"
+    if rest.is_empty() {
+        Some(unit)
+    } else {
+        integer_value(rest)?.checked_add(unit)
+    }
+}
+
+fn percentage_value(text: &str) -> Option<String> {
+    let text = text.trim().to_lowercase();
+    let amount = ["%", "percent", "per cent", "por cento", "porcento"]
+        .iter()
+        .find_map(|suffix| text.strip_suffix(suffix))?;
+
+    number_value(amount)
+}
+
+/// Index into `DurationParts` for a unit word.
+fn duration_unit(word: &str) -> Option<usize> {
+    let unit = match word {
+        "year" | "years" | "yr" | "yrs" | "y" | "ano" | "anos" => 0,
+        "month" | "months" | "mês" | "mes" | "meses" => 1,
+        "week" | "weeks" | "wk" | "wks" | "w" | "semana" | "semanas" => 2,
+        "day" | "days" | "d" | "dia" | "dias" => 3,
+        "hour" | "hours" | "hr" | "hrs" | "h" | "hora" | "horas" => 4,
+        "minute" | "minutes" | "min" | "mins" | "m" | "minuto" | "minutos" => 5,
+        "second" | "seconds" | "sec" | "secs" | "s" | "segundo" | "segundos" => 6,
+        _ => return None,
+    };
+    Some(unit)
+}
+
+/// Splits compact forms like "2h" or "1h30min" into amounts and units.
+fn split_compact(word: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+    for c in word.chars() {
+        match pieces.last_mut() {
+            Some(last) if last.ends_with(|l: char| l.is_ascii_digit()) == c.is_ascii_digit() => {
+                last.push(c)
+            }
+            _ => pieces.push(c.to_string()),
+        }
+    }
+
+    let is_compact = pieces.len() > 1
+        && pieces.iter().enumerate().all(|(i, piece)| {
+            if i % 2 == 0 {
+                piece.parse::<i64>().is_ok()
+            } else {
+                duration_unit(piece).is_some()
+            }
+        });
+
+    if is_compact {
+        pieces
+    } else {
+        vec![word.to_string()]
+    }
+}
+
+fn duration_parts(text: &str) -> Option<DurationParts> {
+    let text = text
+        .to_lowercase()
+        .replace("half an hour", "30 minutes")
+        .replace("meia hora", "30 minutos");
+    let words: Vec<String> = text.split_whitespace().flat_map(split_compact).collect();
+
+    let mut parts: DurationParts = [0; 7];
+    let mut last_unit = None;
+    let mut found = false;
+    let mut i = 0;
+
+    while i < words.len() {
+        let word = words[i].as_str();
+
+        if matches!(word, "and" | "e" | ",") {
+            i += 1;
+            continue;
+        }
+
+        // "an hour and a half", "uma hora e meia"
+        let half = words[i..].starts_with(&["a".to_string(), "half".to_string()]);
+        if half || word == "meia" {
+            let (unit, amount) = match last_unit {
+                Some(4) => (5, 30),
+                Some(5) => (6, 30),
+                Some(3) => (4, 12),
+                _ => return None,
+            };
+            parts[unit] = parts[unit].checked_add(amount)?;
+            i += if half { 2 } else { 1 };
+            last_unit = None;
+            continue;
+        }
+
+        let unit_index = (i + 1..words.len()).find(|&j| duration_unit(&words[j]).is_some())?;
+        // The article counts as one only right before the unit: "an hour", "um dia"
+        let amount = match &words[i..unit_index] {
+            [article] if matches!(article.as_str(), "a" | "an" | "um" | "uma") => 1,
+            amount => integer_value(amount)?,
+        };
+        let unit = duration_unit(&words[unit_index])?;
+
+        parts[unit] = parts[unit].checked_add(amount)?;
+        last_unit = Some(unit);
+        found = true;
+        i = unit_index + 1;
+    }
+
+    found.then_some(parts)
+}
+
+fn format_duration(parts: &DurationParts) -> Option<String> {
+    let [years, months, weeks, days, hours, minutes, seconds] = *parts;
+    let days = weeks.checked_mul(7)?.checked_add(days)?;
+
+    let mut iso = "P".to_string();
+    for (value, designator) in [(years, 'Y'), (months, 'M'), (days, 'D')] {
+        if value != 0 {
+            iso.push_str(&format!("{}{}", value, designator));
+        }
+    }
+
+    if hours != 0 || minutes != 0 || seconds != 0 {
+        iso.push('T');
+        for (value, designator) in [(hours, 'H'), (minutes, 'M'), (seconds, 'S')] {
+            if value != 0 {
+                iso.push_str(&format!("{}{}", value, designator));
+            }
+        }
+    }
+
+    Some(if iso == "P" { "PT0S".to_string() } else { iso })
+}
+
+fn time_value(text: &str) -> Option<NaiveTime> {
+    let mut text = format!(" {} ", text.to_lowercase());
+    for (from, to) in [
+        (" o'clock ", " "),
+        (" oclock ", " "),
+        (" horas ", " "),
+        (" hora ", " "),
+        (" a.m ", " am "),
+        (" p.m ", " pm "),
+    ] {
+        text = text.replace(from, to);
+    }
+
+    let mut words = lower_words(&text);
+    if words
+        .first()
+        .is_some_and(|w| matches!(w.as_str(), "at" | "às" | "as" | "à" | "a"))
+    {
+        words.remove(0);
+    }
+
+    match words.join(" ").as_str() {
+        "noon" | "midday" | "meio-dia" | "meio dia" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" | "meia-noite" | "meia noite" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    // "half past five", "quarter to six"
+    if let [first, second, rest @ ..] = words.as_slice() {
+        let offset = match (first.as_str(), second.as_str()) {
+            ("half", "past") => Some(30),
+            ("quarter", "past") => Some(15),
+            ("quarter", "to") => Some(-15),
+            _ => None,
+        };
+        if let Some(offset) = offset {
+            let hour = time_value(&rest.join(" "))?;
+            let minutes = (hour.hour() * 60 + hour.minute()) as i64 + offset;
+            let minutes = minutes.rem_euclid(24 * 60) as u32;
+            return NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0);
+        }
+    }
+
+    let (mut am, mut pm) = (false, false);
+    let period_start = words.iter().position(|w| {
+        matches!(
+            w.as_str(),
+            "am" | "pm" | "in" | "da" | "de" | "à" | "a" | "at" | "of" | "this"
+        )
+    });
+    if let Some(start) = period_start {
+        let period = words[start..].join(" ");
+        match period.as_str() {
+            "am" | "in the morning" | "da manhã" | "de manhã" | "da manha" | "de manha" => {
+                am = true
+            }
+            "pm" | "in the afternoon" | "in the evening" | "at night" | "this evening"
+            | "da tarde" | "à tarde" | "a tarde" | "de tarde" | "da noite" | "à noite"
+            | "a noite" | "de noite" => pm = true,
+            _ => return None,
+        }
+        words.truncate(start);
+    }
+
+    let mut clock = words.join("");
+    if let Some(stripped) = clock.strip_suffix("am") {
+        am = true;
+        clock = stripped.to_string();
+    } else if let Some(stripped) = clock.strip_suffix("pm") {
+        pm = true;
+        clock = stripped.to_string();
+    }
+
+    let (hour, minute) = match clock.split_once([':', 'h']) {
+        Some((hour, "")) => (hour.parse::<u32>().ok()?, 0),
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => match clock.parse::<u32>() {
+            Ok(hour) => (hour, 0),
+            Err(_) => (u32::try_from(integer_value(&words)?).ok()?, 0),
+        },
+    };
+
+    let hour = match (am, pm) {
+        (true, _) if hour == 12 => 0,
+        (_, true) if hour < 12 => hour + 12,
+        _ => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn weekday_value(word: &str) -> Option<Weekday> {
+    let word = word
+        .strip_suffix("-feira")
+        .or_else(|| word.strip_suffix(" feira"))
+        .unwrap_or(word);
+
+    WEEKDAYS
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, weekday)| *weekday)
+}
+
+fn month_value(word: &str) -> Option<u32> {
+    MONTHS
+        .iter()
+        .find(|(name, _)| *name == word || (word.len() >= 3 && name.starts_with(word)))
+        .map(|(_, month)| *month)
+}
+
+fn add_parts(date: NaiveDate, parts: &DurationParts, sign: i64) -> Option<NaiveDate> {
+    let [years, months, weeks, days, ..] = *parts;
+    let months = years.checked_mul(12)?.checked_add(months)?;
+    let days = weeks.checked_mul(7)?.checked_add(days)?;
+    let months = Months::new(u32::try_from(months).ok()?);
+    let days = Days::new(u64::try_from(days).ok()?);
+
+    if sign < 0 {
+        date.checked_sub_months(months)?.checked_sub_days(days)
+    } else {
+        date.checked_add_months(months)?.checked_add_days(days)
+    }
+}
+
+/// A day of the month with its month name, and an optional year:
+/// "april 15", "15th of april", "15 de abril de 2026".
+fn month_day_value(words: &[String], today: NaiveDate) -> Option<NaiveDate> {
+    let words: Vec<&str> = words
+        .iter()
+        .map(String::as_str)
+        .filter(|w| !matches!(*w, "of" | "de" | "do"))
+        .collect();
+
+    // A full month name wins over an abbreviation, "dez" is also the number ten
+    let month_index = words
+        .iter()
+        .position(|w| MONTHS.iter().any(|(name, _)| name == w))
+        .or_else(|| words.iter().position(|w| month_value(w).is_some()))?;
+    let month = month_value(words[month_index])?;
+
+    let mut day = None;
+    let mut year = None;
+    for (i, word) in words.iter().enumerate() {
+        if i == month_index {
+            continue;
+        }
+        let number = word.trim_end_matches(',').parse::<i64>().ok();
+        let value = number.or_else(|| ordinal_value(word))?;
+
+        if number.is_some_and(|n| n >= 1000) && year.is_none() {
+            year = Some(i32::try_from(value).ok()?);
+        } else if day.is_none() {
+            day = Some(u32::try_from(value).ok()?);
+        } else {
+            return None;
+        }
+    }
+
+    let day = day?;
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+        None => {
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            // Without a year, a date that already passed means next year
+            if date < today {
+                NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+            } else {
+                Some(date)
+            }
+        }
+    }
+}
+
+fn date_value(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let mut words = lower_words(text);
+    if words
+        .first()
+        .is_some_and(|w| matches!(w.as_str(), "on" | "no" | "na" | "for" | "para"))
+    {
+        words.remove(0);
+    }
+    let phrase = words.join(" ");
+
+    let offset = match phrase.as_str() {
+        "today" | "hoje" => Some(0),
+        "tomorrow" | "amanhã" | "amanha" => Some(1),
+        "yesterday" | "ontem" => Some(-1),
+        "day after tomorrow"
+        | "the day after tomorrow"
+        | "depois de amanhã"
+        | "depois de amanha" => Some(2),
+        "day before yesterday" | "the day before yesterday" | "anteontem" => Some(-2),
+        _ => None,
+    };
+    if let Some(offset) = offset {
+        return today.checked_add_signed(chrono::Duration::days(offset));
+    }
+
+    for format in ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%d.%m.%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(&phrase, format) {
+            return Some(date);
+        }
+    }
+
+    // "in 3 days", "daqui a 2 semanas", "3 days ago", "há 3 dias"
+    for prefix in ["in ", "within ", "daqui a ", "dentro de ", "em "] {
+        if let Some(amount) = phrase.strip_prefix(prefix) {
+            return add_parts(today, &duration_parts(amount)?, 1);
+        }
+    }
+    if let Some(amount) = phrase.strip_suffix(" ago") {
+        return add_parts(today, &duration_parts(amount)?, -1);
+    }
+    if let Some(amount) = phrase.strip_prefix("há ").or(phrase.strip_prefix("ha ")) {
+        return add_parts(today, &duration_parts(amount)?, -1);
+    }
+
+    // "next friday", "this friday", "friday", "próxima sexta-feira", "next week"
+    let (next, rest) = match words.split_first() {
+        Some((first, rest))
+            if matches!(
+                first.as_str(),
+                "next" | "próxima" | "próximo" | "proxima" | "proximo"
+            ) =>
+        {
+            (true, rest.join(" "))
+        }
+        Some((first, rest))
+            if matches!(first.as_str(), "this" | "esta" | "este" | "nesta" | "neste") =>
+        {
+            (false, rest.join(" "))
+        }
+        _ => (false, phrase.clone()),
+    };
+
+    if let Some(weekday) = weekday_value(&rest) {
+        let ahead = (weekday.num_days_from_monday() as i64
+            - today.weekday().num_days_from_monday() as i64)
+            .rem_euclid(7);
+        let ahead = if next && ahead == 0 { 7 } else { ahead };
+        return today.checked_add_signed(chrono::Duration::days(ahead));
+    }
+
+    if next && let Some(unit) = duration_unit(&rest) {
+        let mut parts = [0; 7];
+        parts[unit] = 1;
+        return add_parts(today, &parts, 1);
+    }
+
+    month_day_value(&words, today)
+}
+
+/// Integer or decimal number, written with digits or words.
+pub(crate) fn parse_number(text: String) -> Option<String> {
+    number_value(&text)
+}
+
+/// Position in a sequence ("3rd", "third", "terceiro"), as an integer.
+pub(crate) fn parse_ordinal(text: String) -> Option<String> {
+    ordinal_value(&text).map(|value| value.to_string())
+}
+
+/// "50%", "fifty percent", "50 por cento", as the number before the percent sign.
+pub(crate) fn parse_percentage(text: String) -> Option<String> {
+    percentage_value(&text)
+}
+
+/// "1 hour and 30 minutes", "2h", "meia hora", as an ISO-8601 duration (`PT1H30M`).
+pub(crate) fn parse_duration(text: String) -> Option<String> {
+    duration_parts(&text).and_then(|parts| format_duration(&parts))
+}
+
+/// "5pm", "17:30", "às 5 da tarde", "noon", as an ISO-8601 time (`17:30:00`).
+pub(crate) fn parse_time(text: String) -> Option<String> {
+    time_value(&text).map(|time| time.format("%H:%M:%S").to_string())
+}
+
+/// "tomorrow", "next friday", "15 de abril", as an ISO-8601 date (`2025-04-18`). With a time
+/// ("next friday at 5pm", "amanhã às 17h") it becomes a date-time (`2025-04-18T17:00:00`).
+pub(crate) fn parse_date(text: String) -> Option<String> {
+    let today = Local::now().date_naive();
+    let padded = format!(" {} ", text.to_lowercase());
+
+    let split = [" at ", " às ", " as ", " à "]
+        .iter()
+        .find_map(|separator| padded.split_once(separator));
+
+    if let Some((date, time)) = split
+        && let Some(date) = date_value(date, today)
+    {
+        let time = time_value(time)?;
+        return Some(date.and_time(time).format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+
+    date_value(&text, today).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+fn iso_duration_map(iso: &str) -> Option<Map> {
+    let body = iso.strip_prefix('P')?;
+    let (date, time) = body.split_once('T').unwrap_or((body, ""));
+
+    let mut map = Map::new();
+    for key in ["years", "months", "days", "hours", "minutes", "seconds"] {
+        map.insert(key.into(), Dynamic::from_int(0));
+    }
+
+    for (part, keys) in [
+        (date, [('Y', "years"), ('M', "months"), ('D', "days")]),
+        (time, [('H', "hours"), ('M', "minutes"), ('S', "seconds")]),
+    ] {
+        let mut amount = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                amount.push(c);
+                continue;
+            }
+            let (_, key) = keys.iter().find(|(designator, _)| *designator == c)?;
+            map.insert((*key).into(), Dynamic::from_int(amount.parse().ok()?));
+            amount.clear();
+        }
+    }
+
+    map.insert("iso".into(), iso.into());
+    Some(map)
+}
+
+fn time_map(map: &mut Map, time: NaiveTime) {
+    map.insert("hour".into(), Dynamic::from_int(time.hour() as i64));
+    map.insert("minute".into(), Dynamic::from_int(time.minute() as i64));
+    map.insert("second".into(), Dynamic::from_int(time.second() as i64));
+}
+
+/// Script value of a slot produced by a built-in entity: numbers become `int`/`float`,
+/// dates, times and durations become maps with their parts and the `iso` string.
+/// `None` for anything else, which is handed to scripts as the plain string.
+pub(crate) fn typed_value(entity: &str, value: &str) -> Option<Dynamic> {
+    match entity {
+        "number" | "ordinal" | "percentage" => match value.parse::<i64>() {
+            Ok(int) => Some(Dynamic::from_int(int)),
+            Err(_) => value.parse::<f64>().ok().map(Dynamic::from_float),
+        },
+        "date" | "dates" => {
+            let mut map = Map::new();
+            let date = match NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+                Ok(datetime) => {
+                    time_map(&mut map, datetime.time());
+                    datetime.date()
+                }
+                Err(_) => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?,
+            };
+            map.insert("year".into(), Dynamic::from_int(date.year() as i64));
+            map.insert("month".into(), Dynamic::from_int(date.month() as i64));
+            map.insert("day".into(), Dynamic::from_int(date.day() as i64));
+            map.insert("iso".into(), value.into());
+            Some(map.into())
+        }
+        "time" => {
+            let time = NaiveTime::parse_from_str(value, "%H:%M:%S").ok()?;
+            let mut map = Map::new();
+            time_map(&mut map, time);
+            map.insert("iso".into(), value.into());
+            Some(map.into())
+        }
+        "duration" => iso_duration_map(value).map(Dynamic::from),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(parser: fn(String) -> Option<String>, text: &str) -> Option<String> {
+        parser(text.to_string())
+    }
+
+    fn date(text: &str) -> Option<NaiveDate> {
+        // A Wednesday
+        date_value(text, NaiveDate::from_ymd_opt(2025, 4, 16).unwrap())
+    }
+
+    fn ymd(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse(parse_number, "42").as_deref(), Some("42"));
+        assert_eq!(parse(parse_number, "forty two").as_deref(), Some("42"));
+        assert_eq!(
+            parse(parse_number, "quarenta e dois").as_deref(),
+            Some("42")
+        );
+        assert_eq!(parse(parse_number, "minus three").as_deref(), Some("-3"));
+        assert_eq!(parse(parse_number, "3,5").as_deref(), Some("3.5"));
+        assert_eq!(parse(parse_number, "forty banana"), None);
+    }
+
+    #[test]
+    fn articles_are_not_numbers_on_their_own() {
+        assert_eq!(parse(parse_number, "a"), None);
+        assert_eq!(parse(parse_number, "an"), None);
+        // The Portuguese numeral one is spelled like the article
+        assert_eq!(parse(parse_number, "um").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn numbers_that_overflow_are_rejected() {
+        assert_eq!(
+            parse(parse_number, "minus 9223372036854775807").as_deref(),
+            Some("-9223372036854775807")
+        );
+        assert_eq!(parse(parse_ordinal, "9223372036854775807 first"), None);
+        assert_eq!(parse(parse_duration, "9223372036854775807 weeks"), None);
+        assert_eq!(
+            parse(parse_duration, "9223372036854775807 hours and 9 hours"),
+            None
+        );
+        assert_eq!(date("in 999999999999999999 years"), None);
+        assert_eq!(date("in -3 days"), None);
+        assert_eq!(date("april 4294967297"), None);
+        assert_eq!(time_value("4294967313"), None);
+    }
+
+    #[test]
+    fn ordinals() {
+        assert_eq!(parse(parse_ordinal, "3rd").as_deref(), Some("3"));
+        assert_eq!(parse(parse_ordinal, "third").as_deref(), Some("3"));
+        assert_eq!(parse(parse_ordinal, "twenty first").as_deref(), Some("21"));
+        assert_eq!(parse(parse_ordinal, "terceira").as_deref(), Some("3"));
+        assert_eq!(parse(parse_ordinal, "2º").as_deref(), Some("2"));
+        assert_eq!(parse(parse_ordinal, "thirdly"), None);
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(parse(parse_percentage, "50%").as_deref(), Some("50"));
+        assert_eq!(
+            parse(parse_percentage, "fifty percent").as_deref(),
+            Some("50")
+        );
+        assert_eq!(
+            parse(parse_percentage, "12,5 por cento").as_deref(),
+            Some("12.5")
+        );
+        assert_eq!(parse(parse_percentage, "50"), None);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(
+            parse(parse_duration, "1 hour and 30 minutes").as_deref(),
+            Some("PT1H30M")
+        );
+        assert_eq!(parse(parse_duration, "1h30min").as_deref(), Some("PT1H30M"));
+        assert_eq!(parse(parse_duration, "meia hora").as_deref(), Some("PT30M"));
+        assert_eq!(parse(parse_duration, "2 weeks").as_deref(), Some("P14D"));
+        assert_eq!(parse(parse_duration, "three days").as_deref(), Some("P3D"));
+        assert_eq!(parse(parse_duration, "soon"), None);
+    }
+
+    #[test]
+    fn articles_count_as_one_before_a_unit() {
+        assert_eq!(parse(parse_duration, "an hour").as_deref(), Some("PT1H"));
+        assert_eq!(
+            parse(parse_duration, "an hour and a half").as_deref(),
+            Some("PT1H30M")
+        );
+        assert_eq!(
+            parse(parse_duration, "uma hora e meia").as_deref(),
+            Some("PT1H30M")
+        );
+        assert_eq!(parse(parse_duration, "a"), None);
+        assert_eq!(parse(parse_duration, "a a hour"), None);
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse(parse_time, "5pm").as_deref(), Some("17:00:00"));
+        assert_eq!(parse(parse_time, "17:30").as_deref(), Some("17:30:00"));
+        assert_eq!(parse(parse_time, "at 12 am").as_deref(), Some("00:00:00"));
+        assert_eq!(
+            parse(parse_time, "às 5 da tarde").as_deref(),
+            Some("17:00:00")
+        );
+        assert_eq!(
+            parse(parse_time, "half past five").as_deref(),
+            Some("05:30:00")
+        );
+        assert_eq!(
+            parse(parse_time, "quarter to six").as_deref(),
+            Some("05:45:00")
+        );
+        assert_eq!(parse(parse_time, "noon").as_deref(), Some("12:00:00"));
+        assert_eq!(parse(parse_time, "25:00"), None);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date("tomorrow"), ymd(2025, 4, 17));
+        assert_eq!(date("anteontem"), ymd(2025, 4, 14));
+        assert_eq!(date("2025-12-01"), ymd(2025, 12, 1));
+        assert_eq!(date("in 3 days"), ymd(2025, 4, 19));
+        assert_eq!(date("in a week"), ymd(2025, 4, 23));
+        assert_eq!(date("2 weeks ago"), ymd(2025, 4, 2));
+        assert_eq!(date("friday"), ymd(2025, 4, 18));
+        assert_eq!(date("next wednesday"), ymd(2025, 4, 23));
+        assert_eq!(date("próxima sexta-feira"), ymd(2025, 4, 18));
+        assert_eq!(date("15 de abril de 2026"), ymd(2026, 4, 15));
+        // Already passed this year
+        assert_eq!(date("april 1st"), ymd(2026, 4, 1));
+        assert_eq!(date("someday"), None);
+    }
+
+    #[test]
+    fn dates_with_a_time() {
+        let value = parse(parse_date, "2025-05-01 at 5pm");
+        assert_eq!(value.as_deref(), Some("2025-05-01T17:00:00"));
+    }
+
+    #[test]
+    fn typed_values() {
+        assert_eq!(typed_value("number", "42").unwrap().as_int(), Ok(42));
+        assert_eq!(typed_value("number", "2.5").unwrap().as_float(), Ok(2.5));
+
+        let duration = typed_value("duration", "PT1H30M").unwrap().cast::<Map>();
+        assert_eq!(duration["hours"].as_int(), Ok(1));
+        assert_eq!(duration["minutes"].as_int(), Ok(30));
+
+        let date = typed_value("date", "2025-04-18T17:00:00")
+            .unwrap()
+            .cast::<Map>();
+        assert_eq!(date["day"].as_int(), Ok(18));
+        assert_eq!(date["hour"].as_int(), Ok(17));
+
+        assert!(typed_value("locations", "paris").is_none());
+    }
+}
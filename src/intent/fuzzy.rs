@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+pub(crate) enum PatternToken<'p> {
+    Literal(&'p str),
+    Slot(&'p str),
+    Wildcard,
+}
+
+pub(crate) struct FuzzySlot {
+    /// Slot name as written in the pattern
+    pub(crate) name: String,
+    /// Indices of the utterance words the slot covered
+    pub(crate) words: Range<usize>,
+    pub(crate) raw: String,
+    pub(crate) value: String,
+}
+
+pub(crate) struct FuzzyMatch {
+    pub(crate) slots: Vec<FuzzySlot>,
+    /// Words of the utterance that were corrected, mapped to what they were corrected to.
+    pub(crate) corrections: HashMap<String, String>,
+    similarity_sum: f64,
+    compared: usize,
+}
+
+impl FuzzyMatch {
+    fn empty() -> Self {
+        FuzzyMatch {
+            slots: Vec::new(),
+            corrections: HashMap::new(),
+            similarity_sum: 0.0,
+            compared: 0,
+        }
+    }
+
+    /// Mean similarity of every literal token and slot value that was compared.
+    pub(crate) fn similarity(&self) -> f64 {
+        if self.compared == 0 {
+            1.0
+        } else {
+            self.similarity_sum / self.compared as f64
+        }
+    }
+}
+
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Case-insensitive similarity between two strings, from 0 (nothing in common) to 1 (equal).
+pub(crate) fn similarity(a: &str, b: &str) -> f64 {
+    let a = a.to_lowercase();
+    let b = b.to_lowercase();
+    let longest = a.chars().count().max(b.chars().count());
+
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - levenshtein(&a, &b) as f64 / longest as f64
+}
+
+/// Splits an expanded pattern (see `template::expand`) on whitespace, a word written as
+/// `{name}` becomes a slot and `*` a wildcard.
+pub(crate) fn tokenize_pattern(pattern: &str) -> Vec<PatternToken<'_>> {
+    pattern
+        .split_whitespace()
+        .map(|word| {
+            if word.len() > 2 && word.starts_with('{') && word.ends_with('}') {
+                PatternToken::Slot(&word[1..word.len() - 1])
+            } else if word == "*" {
+                PatternToken::Wildcard
+            } else {
+                PatternToken::Literal(word)
+            }
+        })
+        .collect()
+}
+
+/// Aligns the pattern tokens with the words of the utterance, tolerating typos in literal
+/// words. `resolve` receives a slot name and the words it would cover and returns the slot
+/// value with its similarity, or `None` to reject. Slots are lazy, like `.+?` in the regex.
+/// `start` is the index of `words[0]` in the whole utterance.
+pub(crate) fn align<F>(
+    tokens: &[PatternToken],
+    words: &[&str],
+    start: usize,
+    threshold: f64,
+    resolve: &F,
+) -> Option<FuzzyMatch>
+where
+    F: Fn(&str, &str) -> Option<(String, f64)>,
+{
+    let Some(token) = tokens.first() else {
+        return words.is_empty().then(FuzzyMatch::empty);
+    };
+
+    match token {
+        PatternToken::Literal(literal) => {
+            let word = words.first()?;
+            let score = similarity(literal, word);
+            if score < threshold {
+                return None;
+            }
+
+            let mut found = align(&tokens[1..], &words[1..], start + 1, threshold, resolve)?;
+            if !literal.eq_ignore_ascii_case(word) {
+                found
+                    .corrections
+                    .insert(word.to_string(), literal.to_string());
+            }
+            found.similarity_sum += score;
+            found.compared += 1;
+            Some(found)
+        }
+        PatternToken::Slot(name) => {
+            for end in 1..=words.len() {
+                let candidate = words[..end].join(" ");
+                let Some((value, score)) = resolve(name, &candidate) else {
+                    continue;
+                };
+
+                if let Some(mut found) =
+                    align(&tokens[1..], &words[end..], start + end, threshold, resolve)
+                {
+                    if score < 1.0 {
+                        found.corrections.insert(candidate.clone(), value.clone());
+                    }
+                    found.slots.push(FuzzySlot {
+                        name: name.to_string(),
+                        words: start..start + end,
+                        raw: candidate,
+                        value,
+                    });
+                    found.similarity_sum += score;
+                    found.compared += 1;
+                    return Some(found);
+                }
+            }
+
+            None
+        }
+        PatternToken::Wildcard => (1..=words.len())
+            .find_map(|end| align(&tokens[1..], &words[end..], start + end, threshold, resolve)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(_: &str, candidate: &str) -> Option<(String, f64)> {
+        Some((candidate.to_string(), 1.0))
+    }
+
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("flight", ""), 6);
+        assert_eq!(levenshtein("flight", "flight"), 0);
+        assert_eq!(levenshtein("flight", "fligth"), 2);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn similarity_ignores_case() {
+        assert_eq!(similarity("Hello", "hello"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert_eq!(similarity("book", "bok"), 0.75);
+    }
+
+    #[test]
+    fn tokenizes_slots_and_wildcards() {
+        let tokens = tokenize_pattern("fly to {city} * {}");
+        assert!(matches!(tokens[0], PatternToken::Literal("fly")));
+        assert!(matches!(tokens[2], PatternToken::Slot("city")));
+        assert!(matches!(tokens[3], PatternToken::Wildcard));
+        assert!(matches!(tokens[4], PatternToken::Literal("{}")));
+    }
+
+    #[test]
+    fn aligns_words_with_typos() {
+        let tokens = tokenize_pattern("book a flight to {city}");
+        let words = ["bok", "a", "fligt", "to", "new", "york"];
+
+        let found = align(&tokens, &words, 0, 0.7, &exact).unwrap();
+        assert_eq!(found.slots[0].raw, "new york");
+        assert_eq!(found.slots[0].words, 4..6);
+        assert_eq!(found.corrections["bok"], "book");
+        assert_eq!(found.corrections["fligt"], "flight");
+        assert!(found.similarity() < 1.0);
+
+        assert!(align(&tokens, &words, 0, 0.9, &exact).is_none());
+    }
+
+    #[test]
+    fn rejected_slots_and_leftover_words_fail() {
+        let tokens = tokenize_pattern("fly to {city}");
+        let reject = |_: &str, _: &str| None;
+        assert!(align(&tokens, &["fly", "to", "paris"], 0, 0.7, &reject).is_none());
+
+        let tokens = tokenize_pattern("hello");
+        assert!(align(&tokens, &["hello", "there"], 0, 0.7, &exact).is_none());
+
+        let tokens = tokenize_pattern("hello *");
+        let found = align(&tokens, &["hello", "there"], 0, 0.7, &exact).unwrap();
+        assert_eq!(found.similarity(), 1.0);
+    }
+}
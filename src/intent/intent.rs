@@ -1,16 +1,89 @@
 use crate::intent::slot::SlotDefinition;
+use regex::Regex;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// `patterns` and `regex_patterns` are either one list for every language or a map from
+/// language code to its list: `"patterns": {"en": [...], "pt": [...]}`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum LocalizedPatterns {
+    Shared(Vec<String>),
+    PerLanguage(HashMap<String, Vec<String>>),
+}
+
+impl Default for LocalizedPatterns {
+    fn default() -> Self {
+        LocalizedPatterns::Shared(Vec::new())
+    }
+}
+
+impl LocalizedPatterns {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            LocalizedPatterns::Shared(patterns) => patterns.is_empty(),
+            LocalizedPatterns::PerLanguage(map) => map.values().all(Vec::is_empty),
+        }
+    }
+
+    pub(crate) fn languages(&self) -> Vec<&String> {
+        match self {
+            LocalizedPatterns::Shared(_) => Vec::new(),
+            LocalizedPatterns::PerLanguage(map) => map.keys().collect(),
+        }
+    }
+
+    /// The patterns of one language, shared lists apply to all of them.
+    pub(crate) fn for_language(&self, language: Option<&str>) -> Vec<String> {
+        match (self, language) {
+            (LocalizedPatterns::Shared(patterns), _) => patterns.clone(),
+            (LocalizedPatterns::PerLanguage(map), Some(language)) => {
+                map.get(language).cloned().unwrap_or_default()
+            }
+            (LocalizedPatterns::PerLanguage(_), None) => Vec::new(),
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct IntentFile {
     pub(crate) intent: String,
     #[serde(default)]
-    pub(crate) patterns: Vec<String>,
+    pub(crate) patterns: LocalizedPatterns,
+    #[serde(default)]
+    pub(crate) regex_patterns: LocalizedPatterns,
+    /// Same syntax as `patterns`, but matched anywhere in the utterance; a match rejects it
+    #[serde(default)]
+    pub(crate) exclude_patterns: LocalizedPatterns,
+    /// Same syntax as `patterns`, only matched right after one of the `follows` intents
+    #[serde(default)]
+    pub(crate) follow_up_patterns: LocalizedPatterns,
+    /// Intents the follow-up patterns continue, this one when empty
+    #[serde(default)]
+    pub(crate) follows: Vec<String>,
+    /// Words or phrases that must all appear in the utterance
     #[serde(default)]
-    pub(crate) regex_patterns: Vec<String>,
+    pub(crate) required_keywords: LocalizedPatterns,
+    /// Words or phrases that reject the utterance when they appear
+    #[serde(default)]
+    pub(crate) forbidden_keywords: LocalizedPatterns,
     #[serde(default)]
     pub(crate) slots: HashMap<String, serde_json::Value>,
+    /// Slot, as written in the patterns, to the response key asked when it is missing
+    #[serde(default)]
+    pub(crate) required_slots: BTreeMap<String, String>,
+    #[serde(default)]
+    pub(crate) fuzzy_threshold: Option<f64>,
+    /// Minimum similarity for the fallback classifier to pick this intent
+    #[serde(default)]
+    pub(crate) classifier_threshold: Option<f64>,
+    #[serde(default = "default_normalize")]
+    pub(crate) normalize: bool,
+}
+
+fn default_normalize() -> bool {
+    true
 }
 
 impl IntentFile {
@@ -19,10 +92,116 @@ impl IntentFile {
     }
 }
 
+/// A skill-defined slot type, loaded from `entities/<name>.entity`:
+/// ```json
+/// {
+///   "entity": "city",
+///   "values": [{ "value": "new york", "synonyms": ["nyc", "big apple"] }],
+///   "validators": ["[a-z]+ city"]
+/// }
+/// ```
+/// Intents use it as `{entity/city}`.
+#[derive(Debug, Deserialize)]
+pub struct EntityFile {
+    pub(crate) entity: String,
+    #[serde(default)]
+    pub(crate) values: Vec<EntityValue>,
+    /// Regexes matched against the whole slot text, accepting values that are not listed
+    #[serde(default)]
+    pub(crate) validators: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EntityValue {
+    pub(crate) value: String,
+    #[serde(default)]
+    pub(crate) synonyms: Vec<String>,
+}
+
+impl EntityFile {
+    pub fn is_valid(&self) -> bool {
+        (!self.values.is_empty() || !self.validators.is_empty()) && !self.entity.is_empty()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Intent {
     pub(crate) name: String,
+    /// Id of the skill the intent was loaded from
+    pub(crate) skill_id: String,
+    /// Only recognized while the engine is set to this language, `None` for every language
+    pub(crate) language: Option<String>,
+    /// `patterns` of the intent file for this language, normalized when `normalize` is set
     pub(crate) patterns: Vec<String>,
-    pub(crate) regex_patterns: Vec<String>,
+    /// `patterns` compiled by `SlotExtractor::pattern_to_regex`
+    pub(crate) compiled_patterns: Vec<Regex>,
+    /// `regex_patterns` compiled case-insensitively, in the same order
+    pub(crate) compiled_regex_patterns: Vec<Regex>,
+    /// Every phrasing of each pattern, for fuzzy matching and load-time analysis
+    pub(crate) variants: Vec<Vec<String>>,
+    /// Phrasings of the patterns without their slots, the training data of the classifier
+    pub(crate) examples: Vec<String>,
+    pub(crate) exclude_patterns: Vec<Regex>,
+    /// `follow_up_patterns` compiled like `patterns`
+    pub(crate) compiled_follow_ups: Vec<Regex>,
+    pub(crate) follows: Vec<String>,
+    /// Lowercased, and normalized when `normalize` is set
+    pub(crate) required_keywords: Vec<String>,
+    pub(crate) forbidden_keywords: Vec<String>,
     pub(crate) slots: HashMap<String, SlotDefinition>,
+    /// Asked for in order before the intent is dispatched
+    pub(crate) required_slots: BTreeMap<String, String>,
+    pub(crate) fuzzy_threshold: Option<f64>,
+    /// The engine's `classifier_threshold` applies when `None`
+    pub(crate) classifier_threshold: Option<f64>,
+    pub(crate) normalize: bool,
+    /// The intent file, kept to compile the intent again when the normalization changes
+    pub(crate) source: Rc<IntentFile>,
+}
+
+impl Intent {
+    /// Checks the utterance against `exclude_patterns` and the keyword lists, which apply
+    /// whichever pattern matched.
+    pub(crate) fn accepts(&self, text: &str) -> bool {
+        let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+        let contains = |phrase: &String| {
+            let phrase: Vec<&str> = phrase.split_whitespace().collect();
+            !phrase.is_empty() && words.windows(phrase.len()).any(|window| window == phrase)
+        };
+
+        self.required_keywords.iter().all(contains)
+            && !self.forbidden_keywords.iter().any(contains)
+            && !self.exclude_patterns.iter().any(|r| r.is_match(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intent::engine::IntentEngine;
+    use crate::intent::engine::tests::{load, matches};
+
+    #[test]
+    fn keywords_and_exclusions_filter_utterances() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "lights", "patterns": ["turn {state} the * light"], "slots": {"state": ["on", "off"]},
+                "exclude_patterns": ["(car|garage)"], "required_keywords": ["living room"], "forbidden_keywords": ["tomorrow"]}"#,
+        );
+        let intent = &engine.intents[0];
+
+        assert!(intent.accepts("turn on the living room light"));
+        assert!(intent.accepts("turn on the LIVING ROOM light"));
+        assert!(!intent.accepts("turn on the kitchen light"));
+        assert!(!intent.accepts("turn on the living roomba light"));
+        assert!(!intent.accepts("turn on the living room light tomorrow"));
+        assert!(!intent.accepts("turn on the living room car light"));
+        assert!(intent.accepts("turn on the living room carpet light"));
+
+        assert_eq!(
+            matches(&engine, "turn on the living room light"),
+            vec!["lights"]
+        );
+        assert!(matches(&engine, "turn on the kitchen light").is_empty());
+    }
 }
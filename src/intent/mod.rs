@@ -1,5 +1,14 @@
+mod classifier;
+pub mod diagnostics;
 pub mod engine;
+mod entities;
+mod fuzzy;
 pub mod intent;
+mod normalizer;
+mod partial;
+mod prefilter;
+pub mod processor;
 pub mod recognizer;
 mod slot;
 pub mod slot_extrator;
+mod template;
@@ -0,0 +1,689 @@
+use crate::intent::template::TEMPLATE_CHARS;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Punctuation kept when it sits between two alphanumeric characters, so dates (`2025-04-15`),
+/// times (`10:30`), decimals (`3.5`) and words like `o'clock` survive.
+const JOINERS: [char; 5] = ['-', '.', ':', '/', '\''];
+
+const EN_CONTRACTIONS: [(&str, &str); 12] = [
+    ("can't", "can not"),
+    ("won't", "will not"),
+    ("shan't", "shall not"),
+    ("let's", "let us"),
+    ("it's", "it is"),
+    ("what's", "what is"),
+    ("that's", "that is"),
+    ("there's", "there is"),
+    ("here's", "here is"),
+    ("who's", "who is"),
+    ("where's", "where is"),
+    ("how's", "how is"),
+];
+
+const EN_SUFFIXES: [(&str, &str); 6] = [
+    ("n't", " not"),
+    ("'re", " are"),
+    ("'ll", " will"),
+    ("'ve", " have"),
+    ("'m", " am"),
+    ("'d", " would"),
+];
+
+const PT_CONTRACTIONS: [(&str, &str); 8] = [
+    ("pra", "para"),
+    ("pro", "para o"),
+    ("pros", "para os"),
+    ("tá", "está"),
+    ("tô", "estou"),
+    ("né", "não é"),
+    ("vc", "você"),
+    ("q", "que"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum NumberWord {
+    Unit(i64),
+    Ten(i64),
+    Hundred(i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct NormalizerConfig {
+    pub(crate) language: String,
+    pub(crate) nfkc: bool,
+    pub(crate) strip_punctuation: bool,
+    pub(crate) fold_accents: bool,
+    pub(crate) expand_contractions: bool,
+    /// Off by default: it would also turn "uma pizza" into "1 pizza" in free-text slots, and
+    /// the number and duration slots read number words themselves
+    pub(crate) numbers_to_digits: bool,
+}
+
+impl Default for NormalizerConfig {
+    fn default() -> Self {
+        NormalizerConfig {
+            language: "en".to_string(),
+            nfkc: true,
+            strip_punctuation: true,
+            fold_accents: false,
+            expand_contractions: true,
+            numbers_to_digits: false,
+        }
+    }
+}
+
+impl NormalizerConfig {
+    /// Turns a stage on or off by its field name, for configuration read as text.
+    pub(crate) fn set_stage(&mut self, stage: &str, enabled: bool) -> Result<(), String> {
+        let flag = match stage {
+            "nfkc" => &mut self.nfkc,
+            "strip_punctuation" => &mut self.strip_punctuation,
+            "fold_accents" => &mut self.fold_accents,
+            "expand_contractions" => &mut self.expand_contractions,
+            "numbers_to_digits" => &mut self.numbers_to_digits,
+            _ => return Err(format!("Unknown normalizer stage: {}", stage)),
+        };
+        *flag = enabled;
+        Ok(())
+    }
+}
+
+/// A normalized utterance that remembers where each of its words came from, so what is found
+/// in it can be reported as the user wrote it.
+#[derive(Debug, Clone)]
+pub struct Normalized {
+    pub(crate) text: String,
+    original: Vec<char>,
+    /// Character span of every word of `text`, with the span in the original it came from
+    words: Vec<((usize, usize), (usize, usize))>,
+}
+
+impl Normalized {
+    /// Span of the original text that the `start..end` characters of `text` came from. Words
+    /// the normalizer kept the same length map character by character, any other word (an
+    /// expanded contraction, digits for number words...) maps to all of what it came from.
+    pub(crate) fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        let first = self.words.iter().find(|(span, _)| span.1 > start);
+        let last = self.words.iter().rev().find(|(span, _)| span.0 < end);
+        let (Some(&(first, first_original)), Some(&(last, last_original))) = (first, last) else {
+            return (0, 0);
+        };
+        let exact = |span: (usize, usize), original: (usize, usize)| {
+            span.1 - span.0 == original.1 - original.0
+        };
+
+        let original_start = if exact(first, first_original) {
+            first_original.0 + start.saturating_sub(first.0)
+        } else {
+            first_original.0
+        };
+        let original_end = if exact(last, last_original) {
+            last_original.0 + (end - last.0).min(last.1 - last.0)
+        } else {
+            last_original.1
+        };
+
+        (original_start, original_end.max(original_start))
+    }
+
+    pub(crate) fn original_text(&self, start: usize, end: usize) -> String {
+        self.original[start..end].iter().collect()
+    }
+}
+
+/// Cleans up utterances (and the literal parts of patterns) before recognition.
+/// Whitespace is always collapsed, every other stage can be turned off in `NormalizerConfig`.
+#[derive(Clone)]
+pub struct Normalizer {
+    config: NormalizerConfig,
+}
+
+impl Normalizer {
+    pub(crate) fn new(config: NormalizerConfig) -> Self {
+        Normalizer { config }
+    }
+
+    pub(crate) fn config(&self) -> &NormalizerConfig {
+        &self.config
+    }
+
+    /// Same configuration for another language.
+    pub(crate) fn with_language(&self, language: &str) -> Normalizer {
+        Normalizer::new(NormalizerConfig {
+            language: language.to_string(),
+            ..self.config.clone()
+        })
+    }
+
+    pub(crate) fn normalize(&self, text: &str) -> String {
+        self.normalize_aligned(text).text
+    }
+
+    /// Normalizes an utterance word by word, keeping track of the span of `text` every
+    /// normalized word came from.
+    pub(crate) fn normalize_aligned(&self, text: &str) -> Normalized {
+        let original: Vec<char> = text.chars().collect();
+        let mut words = Vec::new();
+        let mut i = 0;
+
+        while i < original.len() {
+            if original[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < original.len() && !original[i].is_whitespace() {
+                i += 1;
+            }
+
+            let token: String = original[start..i].iter().collect();
+            let cleaned = self.clean(&token);
+            let same_length = cleaned.chars().count() == i - start;
+            let mut offset = 0;
+            for part in cleaned.split(' ') {
+                let length = part.chars().count();
+                if !part.trim().is_empty() {
+                    let span = if same_length {
+                        (start + offset, start + offset + length)
+                    } else {
+                        (start, i)
+                    };
+                    words.extend(part.split_whitespace().map(|w| (w.to_string(), span)));
+                }
+                offset += length + 1;
+            }
+        }
+
+        let mut normalized = Normalized {
+            text: String::new(),
+            original,
+            words: Vec::new(),
+        };
+        for (word, span) in self.transform_words(words) {
+            if !normalized.text.is_empty() {
+                normalized.text.push(' ');
+            }
+            let start = normalized.text.chars().count();
+            normalized.text.push_str(&word);
+            normalized
+                .words
+                .push(((start, start + word.chars().count()), span));
+        }
+        normalized
+    }
+
+    /// Normalizes the literal text of a pattern and leaves `{slot}` placeholders, template
+    /// syntax and escaped characters untouched.
+    pub(crate) fn normalize_pattern(&self, pattern: &str) -> String {
+        let mut result = String::new();
+        let mut literal = String::new();
+        let mut chars = pattern.chars();
+
+        while let Some(c) = chars.next() {
+            let verbatim = match c {
+                '\\' => chars.next().map(|escaped| format!("\\{}", escaped)),
+                '{' if chars.clone().any(|c| c == '}') => {
+                    let mut slot = c.to_string();
+                    for c in chars.by_ref() {
+                        slot.push(c);
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                    Some(slot)
+                }
+                c if TEMPLATE_CHARS.contains(&c) => Some(c.to_string()),
+                _ => None,
+            };
+
+            match verbatim {
+                Some(verbatim) => {
+                    result.push_str(&self.normalize_segment(&literal));
+                    result.push_str(&verbatim);
+                    literal.clear();
+                }
+                None => literal.push(c),
+            }
+        }
+
+        result.push_str(&self.normalize_segment(&literal));
+        result.trim().to_string()
+    }
+
+    /// Normalizes a piece of text, keeping a single space at either end if it had any.
+    fn normalize_segment(&self, text: &str) -> String {
+        let text = self.clean(text);
+        let words = text
+            .split_whitespace()
+            .map(|word| (word.to_string(), (0, 0)))
+            .collect();
+        let words: Vec<String> = self
+            .transform_words(words)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect();
+
+        let mut result = words.join(" ");
+        if !result.is_empty() {
+            if text.starts_with(char::is_whitespace) {
+                result.insert(0, ' ');
+            }
+            if text.ends_with(char::is_whitespace) {
+                result.push(' ');
+            }
+        } else if !text.is_empty() {
+            result.push(' ');
+        }
+        result
+    }
+
+    /// The stages working on characters: compatibility forms, punctuation and accents.
+    fn clean(&self, text: &str) -> String {
+        let mut text: String = if self.config.nfkc {
+            text.nfkc().collect()
+        } else {
+            text.to_string()
+        };
+        text = text.replace(['\u{2019}', '\u{2018}'], "'");
+
+        if self.config.strip_punctuation {
+            text = Self::strip_punctuation(&text);
+        }
+
+        if self.config.fold_accents {
+            text = text
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .nfc()
+                .collect();
+        }
+
+        text
+    }
+
+    /// The stages working on whole words, each word carrying the span it came from.
+    fn transform_words(
+        &self,
+        mut words: Vec<(String, (usize, usize))>,
+    ) -> Vec<(String, (usize, usize))> {
+        if self.config.expand_contractions {
+            words = words
+                .iter()
+                .flat_map(|(word, span)| {
+                    self.expand_contraction(word)
+                        .split(' ')
+                        .map(|w| (w.to_string(), *span))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+        }
+
+        if self.config.numbers_to_digits {
+            words = self.numbers_to_digits(&words);
+        }
+
+        words
+    }
+
+    fn is_punctuation(c: char) -> bool {
+        c.is_ascii_punctuation() && !matches!(c, '%' | '+' | '#' | '@' | '&' | '$')
+            || matches!(c, '¿' | '¡' | '«' | '»' | '…' | '“' | '”')
+    }
+
+    /// Whether `c` never survives normalization, wherever it appears.
+    pub(crate) fn always_strips(&self, c: char) -> bool {
+        self.config.strip_punctuation && Self::is_punctuation(c) && !JOINERS.contains(&c)
+    }
+
+    fn strip_punctuation(text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+
+        chars
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                if !Self::is_punctuation(c) {
+                    return c;
+                }
+
+                let joins_words = JOINERS.contains(&c)
+                    && i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|n| n.is_alphanumeric());
+
+                if joins_words { c } else { ' ' }
+            })
+            .collect()
+    }
+
+    fn expand_contraction(&self, word: &str) -> String {
+        let lower = word.to_lowercase();
+
+        match self.config.language.as_str() {
+            "en" => {
+                if let Some((_, full)) = EN_CONTRACTIONS.iter().find(|(c, _)| *c == lower) {
+                    return full.to_string();
+                }
+                for (suffix, full) in EN_SUFFIXES {
+                    if let Some(stem) = lower.strip_suffix(suffix)
+                        && !stem.is_empty()
+                    {
+                        let stem = if word.is_ascii() {
+                            &word[..stem.len()]
+                        } else {
+                            stem
+                        };
+                        return format!("{}{}", stem, full);
+                    }
+                }
+            }
+            "pt" => {
+                if let Some((_, full)) = PT_CONTRACTIONS.iter().find(|(c, _)| *c == lower) {
+                    return full.to_string();
+                }
+            }
+            _ => {}
+        }
+
+        word.to_string()
+    }
+
+    /// Replaces runs of number words ("twenty one", "vinte e um") with their digits.
+    fn numbers_to_digits(
+        &self,
+        words: &[(String, (usize, usize))],
+    ) -> Vec<(String, (usize, usize))> {
+        let text: Vec<&str> = words.iter().map(|(word, _)| word.as_str()).collect();
+        let mut result = Vec::new();
+        let mut i = 0;
+
+        while i < words.len() {
+            match number_run(&text, i, &self.config.language) {
+                Some((value, end)) => {
+                    result.push((value.to_string(), (words[i].1.0, words[end - 1].1.1)));
+                    i = end;
+                }
+                None => {
+                    result.push(words[i].clone());
+                    i += 1;
+                }
+            }
+        }
+
+        result
+    }
+}
+
+fn number_word(word: &str, language: &str) -> Option<NumberWord> {
+    let word = word.to_lowercase();
+
+    let value = match language {
+        "en" => match word.as_str() {
+            "zero" => NumberWord::Unit(0),
+            "one" => NumberWord::Unit(1),
+            "two" => NumberWord::Unit(2),
+            "three" => NumberWord::Unit(3),
+            "four" => NumberWord::Unit(4),
+            "five" => NumberWord::Unit(5),
+            "six" => NumberWord::Unit(6),
+            "seven" => NumberWord::Unit(7),
+            "eight" => NumberWord::Unit(8),
+            "nine" => NumberWord::Unit(9),
+            "ten" => NumberWord::Unit(10),
+            "eleven" => NumberWord::Unit(11),
+            "twelve" => NumberWord::Unit(12),
+            "thirteen" => NumberWord::Unit(13),
+            "fourteen" => NumberWord::Unit(14),
+            "fifteen" => NumberWord::Unit(15),
+            "sixteen" => NumberWord::Unit(16),
+            "seventeen" => NumberWord::Unit(17),
+            "eighteen" => NumberWord::Unit(18),
+            "nineteen" => NumberWord::Unit(19),
+            "twenty" => NumberWord::Ten(20),
+            "thirty" => NumberWord::Ten(30),
+            "forty" => NumberWord::Ten(40),
+            "fifty" => NumberWord::Ten(50),
+            "sixty" => NumberWord::Ten(60),
+            "seventy" => NumberWord::Ten(70),
+            "eighty" => NumberWord::Ten(80),
+            "ninety" => NumberWord::Ten(90),
+            "hundred" => NumberWord::Hundred(100),
+            _ => return None,
+        },
+        "pt" => match word.as_str() {
+            "zero" => NumberWord::Unit(0),
+            "um" | "uma" => NumberWord::Unit(1),
+            "dois" | "duas" => NumberWord::Unit(2),
+            "três" | "tres" => NumberWord::Unit(3),
+            "quatro" => NumberWord::Unit(4),
+            "cinco" => NumberWord::Unit(5),
+            "seis" => NumberWord::Unit(6),
+            "sete" => NumberWord::Unit(7),
+            "oito" => NumberWord::Unit(8),
+            "nove" => NumberWord::Unit(9),
+            "dez" => NumberWord::Unit(10),
+            "onze" => NumberWord::Unit(11),
+            "doze" => NumberWord::Unit(12),
+            "treze" => NumberWord::Unit(13),
+            "catorze" | "quatorze" => NumberWord::Unit(14),
+            "quinze" => NumberWord::Unit(15),
+            "dezasseis" | "dezesseis" => NumberWord::Unit(16),
+            "dezassete" | "dezessete" => NumberWord::Unit(17),
+            "dezoito" => NumberWord::Unit(18),
+            "dezanove" | "dezenove" => NumberWord::Unit(19),
+            "vinte" => NumberWord::Ten(20),
+            "trinta" => NumberWord::Ten(30),
+            "quarenta" => NumberWord::Ten(40),
+            "cinquenta" => NumberWord::Ten(50),
+            "sessenta" => NumberWord::Ten(60),
+            "setenta" => NumberWord::Ten(70),
+            "oitenta" => NumberWord::Ten(80),
+            "noventa" => NumberWord::Ten(90),
+            "cem" => NumberWord::Hundred(100),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Parses the run of number words starting at `start` ("twenty one", "vinte e um") and
+/// returns its value with the index of the first word after it.
+pub(crate) fn number_run<S: AsRef<str>>(
+    words: &[S],
+    start: usize,
+    language: &str,
+) -> Option<(i64, usize)> {
+    let connector = match language {
+        "pt" => "e",
+        _ => "and",
+    };
+
+    let mut total = 0;
+    let mut last: Option<NumberWord> = None;
+    let mut j = start;
+    // After the last number word, a connector that no number followed is not part of the run
+    let mut end = start;
+
+    while j < words.len() {
+        let word = words[j].as_ref();
+        let kinds: Option<Vec<NumberWord>> = word
+            .split('-')
+            .map(|part| number_word(part, language))
+            .collect();
+
+        if let Some(kinds) = kinds {
+            let (mut run_total, mut run_last) = (total, last);
+            let mut accepted = true;
+            for kind in kinds {
+                let fits = match (run_last, kind) {
+                    (None, _) => true,
+                    (Some(NumberWord::Ten(_)), NumberWord::Unit(v)) => v < 10,
+                    (Some(NumberWord::Hundred(_)), NumberWord::Unit(_))
+                    | (Some(NumberWord::Hundred(_)), NumberWord::Ten(_)) => true,
+                    (Some(NumberWord::Unit(_)), NumberWord::Hundred(_)) => run_total < 10,
+                    _ => false,
+                };
+                if !fits {
+                    accepted = false;
+                    break;
+                }
+
+                run_total = match kind {
+                    NumberWord::Hundred(v) => run_total.max(1) * v,
+                    NumberWord::Unit(v) | NumberWord::Ten(v) => run_total + v,
+                };
+                run_last = Some(kind);
+            }
+
+            if !accepted {
+                break;
+            }
+            (total, last) = (run_total, run_last);
+            j += 1;
+            end = j;
+        } else if last.is_some()
+            && word.eq_ignore_ascii_case(connector)
+            && words
+                .get(j + 1)
+                .is_some_and(|next| number_word(next.as_ref(), language).is_some())
+        {
+            j += 1;
+        } else {
+            break;
+        }
+    }
+
+    last.map(|_| (total, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer(language: &str) -> Normalizer {
+        Normalizer::new(NormalizerConfig::default()).with_language(language)
+    }
+
+    fn without(stage: &str) -> Normalizer {
+        let mut config = NormalizerConfig::default();
+        config.set_stage(stage, false).unwrap();
+        Normalizer::new(config)
+    }
+
+    fn with(stage: &str, language: &str) -> Normalizer {
+        let mut config = NormalizerConfig::default();
+        config.set_stage(stage, true).unwrap();
+        Normalizer::new(config).with_language(language)
+    }
+
+    #[test]
+    fn collapses_whitespace() {
+        assert_eq!(
+            normalizer("en").normalize("  turn   on\tthe light "),
+            "turn on the light"
+        );
+    }
+
+    #[test]
+    fn nfkc_folds_compatibility_characters() {
+        assert_eq!(normalizer("en").normalize("ﬁnd room ２"), "find room 2");
+        assert_eq!(without("nfkc").normalize("ﬁnd"), "ﬁnd");
+    }
+
+    #[test]
+    fn punctuation_is_stripped_unless_it_joins_words() {
+        let normalizer = normalizer("en");
+        assert_eq!(normalizer.normalize("Hello, Avi!"), "Hello Avi");
+        assert_eq!(
+            normalizer.normalize("at 10:30 on 2025-04-15"),
+            "at 10:30 on 2025-04-15"
+        );
+        assert_eq!(normalizer.normalize("50% off"), "50% off");
+        assert_eq!(
+            without("strip_punctuation").normalize("Hello, Avi!"),
+            "Hello, Avi!"
+        );
+    }
+
+    #[test]
+    fn accents_are_only_folded_when_enabled() {
+        assert_eq!(normalizer("pt").normalize("está"), "está");
+
+        let mut config = NormalizerConfig::default();
+        config.set_stage("fold_accents", true).unwrap();
+        let folding = Normalizer::new(config).with_language("pt");
+        assert_eq!(folding.normalize("está ação"), "esta acao");
+    }
+
+    #[test]
+    fn contractions_expand_per_language() {
+        assert_eq!(
+            normalizer("en").normalize("I can't, it's late"),
+            "I can not it is late"
+        );
+        assert_eq!(normalizer("en").normalize("we're here"), "we are here");
+        assert_eq!(normalizer("pt").normalize("vou pra casa"), "vou para casa");
+        assert_eq!(normalizer("pt").normalize("can't"), "can't");
+        assert_eq!(without("expand_contractions").normalize("it's"), "it's");
+    }
+
+    #[test]
+    fn number_words_only_become_digits_when_enabled() {
+        let digits = with("numbers_to_digits", "en");
+        assert_eq!(digits.normalize("set twenty one alarms"), "set 21 alarms");
+        assert_eq!(digits.normalize("two hundred and five"), "205");
+        assert_eq!(
+            with("numbers_to_digits", "pt").normalize("vinte e um graus"),
+            "21 graus"
+        );
+        assert_eq!(normalizer("pt").normalize("uma pizza"), "uma pizza");
+        assert_eq!(normalizer("en").normalize("twenty one"), "twenty one");
+    }
+
+    #[test]
+    fn a_connector_without_a_number_after_it_stays() {
+        let digits = with("numbers_to_digits", "en");
+        assert_eq!(digits.normalize("one and two"), "1 and 2");
+        assert_eq!(digits.normalize("five and then six"), "5 and then 6");
+        assert_eq!(number_run(&["vinte", "e", "casa"], 0, "pt"), Some((20, 1)));
+    }
+
+    #[test]
+    fn normalized_words_point_back_at_the_original() {
+        let aligned =
+            with("numbers_to_digits", "en").normalize_aligned("Hello,  Avi! it's twenty one");
+        assert_eq!(aligned.text, "Hello Avi it is 21");
+
+        assert_eq!(aligned.original_span(6, 9), (8, 11));
+        assert_eq!(aligned.original_span(6, 8), (8, 10));
+        assert_eq!(aligned.original_span(10, 12), (13, 17));
+        assert_eq!(aligned.original_span(16, 18), (18, 28));
+        assert_eq!(aligned.original_span(6, 15), (8, 17));
+        assert_eq!(aligned.original_text(18, 28), "twenty one");
+    }
+
+    #[test]
+    fn patterns_keep_their_syntax() {
+        let normalizer = normalizer("en");
+        assert_eq!(
+            normalizer.normalize_pattern("what's the weather in {city}?"),
+            "what is the weather in {city}"
+        );
+        assert_eq!(
+            normalizer.normalize_pattern("[please] (turn|switch) on * \\*"),
+            "[please] (turn|switch) on * \\*"
+        );
+    }
+
+    #[test]
+    fn unknown_stages_are_rejected() {
+        assert!(
+            NormalizerConfig::default()
+                .set_stage("lowercase", true)
+                .is_err()
+        );
+    }
+}
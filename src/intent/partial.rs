@@ -0,0 +1,101 @@
+use crate::intent::fuzzy::{FuzzySlot, PatternToken};
+
+/// How far an unfinished utterance got into a pattern phrasing.
+pub(crate) struct PrefixMatch {
+    /// Slots followed by more of the pattern, so their value is settled
+    pub(crate) slots: Vec<FuzzySlot>,
+    /// Pattern tokens the words covered
+    pub(crate) matched_tokens: usize,
+}
+
+impl PrefixMatch {
+    fn empty() -> Self {
+        PrefixMatch {
+            slots: Vec::new(),
+            matched_tokens: 0,
+        }
+    }
+}
+
+/// Matches the words of an unfinished utterance against the start of a pattern phrasing.
+/// Returns `None` unless more words could still complete the pattern; a complete match is
+/// left to the regular recognizer. With `last_word_partial` the last word may be cut short
+/// ("boo" for "book"). `resolve` gets a slot name, its text and whether the slot is still
+/// open at the end of the utterance, and returns the slot value or `None` to reject.
+/// `start` is the index of `words[0]` in the whole utterance.
+pub(crate) fn prefix_align<F>(
+    tokens: &[PatternToken],
+    words: &[&str],
+    start: usize,
+    last_word_partial: bool,
+    resolve: &F,
+) -> Option<PrefixMatch>
+where
+    F: Fn(&str, &str, bool) -> Option<String>,
+{
+    let token = tokens.first()?;
+    if words.is_empty() {
+        return Some(PrefixMatch::empty());
+    }
+
+    match token {
+        PatternToken::Literal(literal) => {
+            let word = words[0];
+            if literal.eq_ignore_ascii_case(word) {
+                let mut found = prefix_align(
+                    &tokens[1..],
+                    &words[1..],
+                    start + 1,
+                    last_word_partial,
+                    resolve,
+                )?;
+                found.matched_tokens += 1;
+                Some(found)
+            } else if words.len() == 1
+                && last_word_partial
+                && literal.to_lowercase().starts_with(&word.to_lowercase())
+            {
+                Some(PrefixMatch::empty())
+            } else {
+                None
+            }
+        }
+        PatternToken::Slot(_) | PatternToken::Wildcard => {
+            for end in 1..words.len() {
+                let candidate = words[..end].join(" ");
+                let value = match token {
+                    PatternToken::Slot(name) => match resolve(name, &candidate, false) {
+                        Some(value) => Some((name, value)),
+                        None => continue,
+                    },
+                    _ => None,
+                };
+
+                if let Some(mut found) = prefix_align(
+                    &tokens[1..],
+                    &words[end..],
+                    start + end,
+                    last_word_partial,
+                    resolve,
+                ) {
+                    found.matched_tokens += 1;
+                    if let Some((name, value)) = value {
+                        found.slots.push(FuzzySlot {
+                            name: name.to_string(),
+                            words: start..start + end,
+                            raw: candidate,
+                            value,
+                        });
+                    }
+                    return Some(found);
+                }
+            }
+
+            // The slot takes the rest of the utterance and may still be growing
+            if let PatternToken::Slot(name) = token {
+                resolve(name, &words.join(" "), true)?;
+            }
+            Some(PrefixMatch::empty())
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::intent::intent::Intent;
+use regex::{RegexSet, RegexSetBuilder, SetMatches};
+
+/// Size limit for the combined set, a few hundred skills easily go over the regex default.
+const SET_SIZE_LIMIT: usize = 256 * (1 << 20);
+
+/// One `RegexSet` over every compiled pattern of every intent, so a single pass over the
+/// utterance tells which patterns are worth running capture extraction on.
+pub(crate) struct Prefilter {
+    set: Option<RegexSet>,
+    /// Index of the first pattern of each intent in the set. The patterns of an intent are
+    /// laid out as `compiled_patterns` followed by `compiled_regex_patterns`.
+    offsets: Vec<usize>,
+}
+
+pub(crate) struct Candidates {
+    matches: Option<SetMatches>,
+}
+
+impl Prefilter {
+    pub(crate) fn build(intents: &[Intent]) -> Self {
+        let mut offsets = Vec::with_capacity(intents.len());
+        let mut sources = Vec::new();
+
+        for intent in intents {
+            offsets.push(sources.len());
+            sources.extend(intent.compiled_patterns.iter().map(|r| r.as_str()));
+            sources.extend(intent.compiled_regex_patterns.iter().map(|r| r.as_str()));
+        }
+
+        // Without a set every pattern is a candidate, which is how recognition used to work.
+        let set = match RegexSetBuilder::new(sources)
+            .case_insensitive(true)
+            .size_limit(SET_SIZE_LIMIT)
+            .build()
+        {
+            Ok(set) => Some(set),
+            Err(err) => {
+                eprintln!("Could not build the intent prefilter: {}", err);
+                None
+            }
+        };
+
+        Prefilter { set, offsets }
+    }
+
+    pub(crate) fn candidates(&self, text: &str) -> Candidates {
+        Candidates {
+            matches: self.set.as_ref().map(|set| set.matches(text)),
+        }
+    }
+
+    pub(crate) fn is_candidate(
+        &self,
+        candidates: &Candidates,
+        intent_index: usize,
+        pattern_index: usize,
+    ) -> bool {
+        match &candidates.matches {
+            Some(matches) => matches.matched(self.offsets[intent_index] + pattern_index),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intent::engine::IntentEngine;
+    use crate::intent::engine::tests::load;
+
+    #[test]
+    fn candidates_are_the_patterns_that_match() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "patterns": ["[please] book [a] flight to {city}", "fly to {city}"], "regex_patterns": ["flight from (?P<origin>\\w+) to (?P<destination>\\w+)"], "slots": {"city": "*", "origin": "*", "destination": "*"}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "hi", "patterns": ["(hi|hello|hey) [avi]"]}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "timer", "patterns": ["set a timer for {default/duration}", "* timer"]}"#,
+        );
+
+        let utterances = [
+            "please book a flight to paris",
+            "Book Flight To Lisbon",
+            "fly to rome",
+            "flight from porto to faro",
+            "hello avi",
+            "hey",
+            "say hello to avi",
+            "set a timer for 5 minutes",
+            "egg timer",
+            "",
+        ];
+
+        let prefilter = engine.prefilter();
+        for text in utterances {
+            let candidates = prefilter.candidates(text);
+            for (index, intent) in engine.intents.iter().enumerate() {
+                let regexes = intent
+                    .compiled_patterns
+                    .iter()
+                    .chain(&intent.compiled_regex_patterns);
+                for (i, regex) in regexes.enumerate() {
+                    assert_eq!(
+                        prefilter.is_candidate(&candidates, index, i),
+                        regex.is_match(text),
+                        "{:?} against {}",
+                        text,
+                        regex
+                    );
+                }
+            }
+        }
+    }
+}
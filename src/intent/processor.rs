@@ -0,0 +1,152 @@
+use crate::skills::avi_script::avi_engine::call_avi_script_fn;
+use rhai::{AST, Engine};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// A slot processor written in AviScript, named `module::function` in the intent file.
+/// The function gets the slot text and returns the value to use, or `()`/`false` to reject
+/// the match.
+#[derive(Clone)]
+pub struct ScriptProcessor {
+    engine: Rc<Engine>,
+    ast: Rc<AST>,
+    function: String,
+    skill_path: PathBuf,
+}
+
+impl fmt::Debug for ScriptProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptProcessor")
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+impl ScriptProcessor {
+    pub(crate) fn process(&self, text: String) -> Option<String> {
+        // Only the function runs, the module's top-level statements are not executed again
+        let result = call_avi_script_fn(
+            &self.engine,
+            &self.ast,
+            &self.function,
+            self.skill_path.clone(),
+            (text.clone(),),
+        );
+
+        match result {
+            Ok(value) if value.is_unit() => None,
+            Ok(value) if value.is_bool() => value.as_bool().unwrap().then_some(text),
+            Ok(value) if value.is_string() => Some(value.into_string().unwrap()),
+            Ok(value) => Some(value.to_string()),
+            Err(err) => {
+                eprintln!("Slot processor {} failed: {}", self.function, err);
+                None
+            }
+        }
+    }
+}
+
+/// Resolves `module::function` processors against the `.avi` modules of one skill, with the
+/// skill's own engine. Each module is compiled once.
+pub struct ProcessorLoader {
+    engine: Rc<Engine>,
+    skill_path: PathBuf,
+    modules: RefCell<HashMap<String, Rc<AST>>>,
+}
+
+impl ProcessorLoader {
+    pub(crate) fn new(engine: Rc<Engine>, skill_path: PathBuf) -> Self {
+        ProcessorLoader {
+            engine,
+            skill_path,
+            modules: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn load(&self, name: &str) -> Result<ScriptProcessor, Box<dyn Error>> {
+        let (module, function) = name
+            .split_once("::")
+            .ok_or_else(|| format!("Invalid processor {} (expected module::function)", name))?;
+
+        let cached = self.modules.borrow().get(module).cloned();
+        let ast = match cached {
+            Some(ast) => ast,
+            None => {
+                let path = self.skill_path.join(format!("{}.avi", module));
+                let ast = Rc::new(self.engine.compile_file(path)?);
+                self.modules
+                    .borrow_mut()
+                    .insert(module.to_string(), ast.clone());
+                ast
+            }
+        };
+
+        if !ast.iter_functions().any(|f| f.name == function) {
+            return Err(format!("Processor {} is not defined in {}.avi", name, module).into());
+        }
+
+        Ok(ScriptProcessor {
+            engine: self.engine.clone(),
+            ast,
+            function: function.to_string(),
+            skill_path: self.skill_path.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::manager::tests::working_dir_lock;
+    use std::fs;
+
+    fn loader() -> ProcessorLoader {
+        let folder = std::env::temp_dir().join("avi-test-processors");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(
+            folder.join("slots.avi"),
+            r#"
+fn shout(text) { text.to_upper() }
+fn nothing(text) { () }
+fn even(text) { parse_int(text) % 2 == 0 }
+fn double(text) { parse_int(text) * 2 }
+fn broken(text) { throw "broken"; }
+"#,
+        )
+        .unwrap();
+        ProcessorLoader::new(Rc::new(Engine::new()), folder)
+    }
+
+    #[test]
+    fn script_results_become_slot_values() {
+        let _lock = working_dir_lock();
+        let loader = loader();
+        let process = |name: &str, text: &str| loader.load(name).unwrap().process(text.to_string());
+
+        assert_eq!(process("slots::shout", "paris").as_deref(), Some("PARIS"));
+        assert_eq!(process("slots::double", "21").as_deref(), Some("42"));
+        assert_eq!(process("slots::even", "4").as_deref(), Some("4"));
+        assert_eq!(process("slots::even", "5"), None);
+        assert_eq!(process("slots::nothing", "paris"), None);
+        assert_eq!(process("slots::broken", "paris"), None);
+    }
+
+    #[test]
+    fn processors_must_name_a_defined_function() {
+        let _lock = working_dir_lock();
+        let loader = loader();
+
+        assert!(loader.load("shout").is_err());
+        assert!(loader.load("slots::missing").is_err());
+        assert!(loader.load("other::shout").is_err());
+
+        // The module is compiled once, later processors use it as it was
+        loader.load("slots::shout").unwrap();
+        fs::remove_file(loader.skill_path.join("slots.avi")).unwrap();
+        assert!(loader.load("slots::double").is_ok());
+    }
+}
@@ -1,5 +1,43 @@
 use crate::intent::engine::IntentEngine;
+use crate::intent::intent::Intent;
+use crate::intent::normalizer::{NormalizerConfig, number_run};
 use crate::intent::slot_extrator::{ExtractedSlots, SlotExtractor};
+use rhai::Dynamic;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchState {
+    /// A pattern matches the text as it is
+    Complete,
+    /// The text is the beginning of a pattern, more words are needed
+    NeedsMore,
+}
+
+/// A candidate for an utterance that is still being said, with the slots filled so far.
+#[derive(Debug, Clone)]
+pub struct PartialMatch {
+    pub(crate) intent: ExtractedSlots,
+    pub(crate) state: MatchState,
+}
+
+/// Result of `Recognizer::recognize_partial`: complete matches first, best first, then the
+/// intents that need more input, by how much of their pattern is covered.
+pub struct PartialRecognition {
+    pub(crate) matches: Vec<PartialMatch>,
+}
+
+impl PartialRecognition {
+    /// The intent to act on without waiting for more input: the only complete candidate,
+    /// with no intent, itself included, still able to match a longer pattern.
+    pub(crate) fn unambiguous(&self) -> Option<&ExtractedSlots> {
+        let first = self.matches.first()?;
+        let settled = self
+            .matches
+            .iter()
+            .all(|m| m.state == MatchState::Complete && m.intent.intent == first.intent.intent);
+        (first.state == MatchState::Complete && settled).then_some(&first.intent)
+    }
+}
 
 pub struct Recognizer<'a> {
     intent_manager: &'a IntentEngine,
@@ -10,35 +48,809 @@ impl<'a> Recognizer<'a> {
     pub(crate) fn new(intent_manager: &'a IntentEngine) -> Self {
         Recognizer {
             intent_manager,
-            slot_extractor: SlotExtractor::new(&intent_manager.default_slots),
+            slot_extractor: SlotExtractor::new(
+                &intent_manager.default_slots,
+                &intent_manager.entities,
+            ),
         }
     }
 
-    pub(crate) fn recognize(&self, text: &str) -> Vec<ExtractedSlots> {
+    pub(crate) fn recognize(&self, raw_text: &str) -> Vec<ExtractedSlots> {
+        self.recognize_with(raw_text, true)
+    }
+
+    /// Every match for the utterance, best first. With `fallback` the classifier is tried when
+    /// no pattern matches. A pattern that matched but had its slots rejected, by an entity or a
+    /// processor, also rules out the classifier: the utterance was understood and refused.
+    pub(crate) fn recognize_with(&self, raw_text: &str, fallback: bool) -> Vec<ExtractedSlots> {
         let mut results = Vec::new();
+        let mut rejected = false;
+        let aligned = self.intent_manager.normalizer.normalize_aligned(raw_text);
+        let normalized = &aligned.text;
+
+        let prefilter = self.intent_manager.prefilter();
+        let normalized_candidates = prefilter.candidates(normalized);
+        let raw_candidates = prefilter.candidates(raw_text);
+
+        for (index, intent) in self.intent_manager.intents.iter().enumerate() {
+            if intent
+                .language
+                .as_ref()
+                .is_some_and(|l| *l != self.intent_manager.language())
+            {
+                continue;
+            }
+
+            let (text, candidates) = if intent.normalize {
+                (normalized.as_str(), &normalized_candidates)
+            } else {
+                (raw_text, &raw_candidates)
+            };
+
+            let first_result = results.len();
 
-        for intent in &self.intent_manager.intents {
             // 1) Try plain patterns
-            for pat in &intent.patterns {
-                if let Some(slots) =
-                    self.slot_extractor
-                        .extract_from_pattern(pat, text, &intent.name, &intent.slots)
+            for (i, regex) in intent.compiled_patterns.iter().enumerate() {
+                let exact = if prefilter.is_candidate(candidates, index, i) {
+                    self.slot_extractor.extract_from_pattern(
+                        regex,
+                        text,
+                        &intent.name,
+                        &intent.slots,
+                    )
+                } else {
+                    None
+                };
+
+                if let Some(slots) = exact {
+                    results.push(slots);
+                    continue;
+                }
+                rejected |= prefilter.is_candidate(candidates, index, i) && regex.is_match(text);
+                if let Some(threshold) = intent.fuzzy_threshold
+                    && let Some(slots) = self.slot_extractor.extract_fuzzy_from_pattern(
+                        &intent.variants[i],
+                        text,
+                        &intent.name,
+                        &intent.slots,
+                        threshold,
+                    )
                 {
                     results.push(slots);
                 }
             }
 
             // 2) Try regex patterns
-            for rx in &intent.regex_patterns {
-                if let Some(slots) =
-                    self.slot_extractor
-                        .extract_from_regex(rx, text, &intent.name, &intent.slots)
-                {
-                    results.push(slots);
+            for (i, regex) in intent.compiled_regex_patterns.iter().enumerate() {
+                if !prefilter.is_candidate(candidates, index, intent.compiled_patterns.len() + i) {
+                    continue;
                 }
+
+                match self.slot_extractor.extract_from_regex(
+                    regex,
+                    text,
+                    &intent.name,
+                    &intent.slots,
+                ) {
+                    Some(slots) => results.push(slots),
+                    None => rejected |= regex.is_match(text),
+                }
+            }
+
+            // 3) Drop the matches if the utterance fails the intent's exclusions and keywords
+            if results.len() > first_result && !intent.accepts(text) {
+                results.truncate(first_result);
             }
+
+            if intent.normalize {
+                for result in &mut results[first_result..] {
+                    result.restore_original(&aligned);
+                }
+            }
+        }
+
+        if results.is_empty() && fallback && !rejected {
+            results = self.classify(normalized, raw_text);
+        }
+
+        for result in &mut results {
+            result.language = self.intent_manager.language().to_string();
         }
 
+        // Highest confidence first; the sort is stable so ties keep file-load order.
+        results.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
         results
     }
+
+    /// Fallback for utterances no pattern matched: intents whose examples are close enough,
+    /// without slots since there is no pattern to take them from.
+    fn classify(&self, normalized: &str, raw_text: &str) -> Vec<ExtractedSlots> {
+        let intents = &self.intent_manager.intents;
+
+        self.intent_manager
+            .classifier()
+            .classify(normalized)
+            .into_iter()
+            .filter(|(index, similarity)| {
+                let intent = &intents[*index];
+                let text = if intent.normalize {
+                    normalized
+                } else {
+                    raw_text
+                };
+                *similarity
+                    >= intent
+                        .classifier_threshold
+                        .unwrap_or(self.intent_manager.classifier_threshold)
+                    && intent.accepts(text)
+                    && intent
+                        .language
+                        .as_ref()
+                        .is_none_or(|l| l == self.intent_manager.language())
+            })
+            .map(|(index, similarity)| ExtractedSlots {
+                intent: intents[index].name.clone(),
+                slots: HashMap::new(),
+                confidence: similarity,
+                corrections: HashMap::new(),
+                language: String::new(),
+            })
+            .collect()
+    }
+
+    /// Recognition for incremental transcripts. Intents a pattern matches are `Complete`, the
+    /// ones whose patterns the text is the beginning of `NeedsMore`. A complete intent is also
+    /// listed as `NeedsMore` while a longer pattern of it can still match. The last word counts
+    /// as possibly unfinished unless the text ends with whitespace.
+    pub(crate) fn recognize_partial(&self, raw_text: &str) -> PartialRecognition {
+        let mut matches: Vec<PartialMatch> = self
+            .recognize_with(raw_text, false)
+            .into_iter()
+            .map(|intent| PartialMatch {
+                intent,
+                state: MatchState::Complete,
+            })
+            .collect();
+
+        let aligned = self.intent_manager.normalizer.normalize_aligned(raw_text);
+        let last_word_partial = !raw_text.ends_with(char::is_whitespace);
+        let mut pending = Vec::new();
+
+        for intent in &self.intent_manager.intents {
+            let other_language = intent
+                .language
+                .as_ref()
+                .is_some_and(|l| *l != self.intent_manager.language());
+            if other_language {
+                continue;
+            }
+
+            let text = if intent.normalize {
+                aligned.text.as_str()
+            } else {
+                raw_text.trim()
+            };
+
+            let best = intent
+                .variants
+                .iter()
+                .flatten()
+                .filter_map(|variant| {
+                    self.slot_extractor.extract_prefix_from_variant(
+                        variant,
+                        text,
+                        &intent.name,
+                        &intent.slots,
+                        last_word_partial,
+                    )
+                })
+                .max_by(|a, b| a.confidence.total_cmp(&b.confidence));
+
+            if let Some(mut best) = best {
+                if intent.normalize {
+                    best.restore_original(&aligned);
+                }
+                best.language = self.intent_manager.language().to_string();
+                pending.push(PartialMatch {
+                    intent: best,
+                    state: MatchState::NeedsMore,
+                });
+            }
+        }
+
+        pending.sort_by(|a, b| b.intent.confidence.total_cmp(&a.intent.confidence));
+        matches.extend(pending);
+
+        PartialRecognition { matches }
+    }
+
+    pub(crate) fn language(&self) -> &str {
+        self.intent_manager.language()
+    }
+
+    pub(crate) fn normalizer_config(&self) -> &NormalizerConfig {
+        self.intent_manager.normalizer.config()
+    }
+
+    pub(crate) fn normalize(&self, text: &str) -> String {
+        self.intent_manager.normalizer.normalize(text)
+    }
+
+    pub(crate) fn knows_slot(&self, slot: &str) -> bool {
+        self.slot_extractor.has_shared_slot(slot)
+    }
+
+    /// Typed value of a shared slot, like `default/number`, found in an answer.
+    pub(crate) fn parse_answer(&self, slot: &str, answer: &str) -> Option<Dynamic> {
+        let text = self.normalize(answer);
+        self.slot_extractor
+            .extract_answer(slot, &text, &HashMap::new())
+            .map(|value| value.typed())
+    }
+
+    fn find_intent(&self, recognized: &ExtractedSlots) -> Option<&Intent> {
+        self.intent_manager.intents.iter().find(|intent| {
+            intent.name == recognized.intent
+                && intent
+                    .language
+                    .as_ref()
+                    .is_none_or(|l| *l == recognized.language)
+        })
+    }
+
+    /// The `required_slots` of the recognized intent that are still empty, with the
+    /// response key to ask for each.
+    pub(crate) fn missing_slots(&self, recognized: &ExtractedSlots) -> Vec<(String, String)> {
+        let Some(intent) = self.find_intent(recognized) else {
+            return Vec::new();
+        };
+
+        intent
+            .required_slots
+            .iter()
+            .filter(|(slot, _)| !recognized.slots.contains_key(&slot.replacen('/', "_", 1)))
+            .map(|(slot, prompt)| (slot.clone(), prompt.clone()))
+            .collect()
+    }
+
+    /// Runs slot extraction for one slot on the user's answer to its prompt. The offsets of
+    /// the slot are then relative to the answer. Returns whether the slot was filled.
+    pub(crate) fn fill_slot(
+        &self,
+        recognized: &mut ExtractedSlots,
+        slot: &str,
+        answer: &str,
+    ) -> bool {
+        let Some(intent) = self.find_intent(recognized) else {
+            return false;
+        };
+
+        let aligned = self.intent_manager.normalizer.normalize_aligned(answer);
+        let text = if intent.normalize {
+            aligned.text.clone()
+        } else {
+            answer.trim().to_string()
+        };
+
+        match self
+            .slot_extractor
+            .extract_answer(slot, &text, &intent.slots)
+        {
+            Some(mut value) => {
+                if intent.normalize {
+                    value.restore_original(&aligned);
+                }
+                recognized.slots.insert(slot.replacen('/', "_", 1), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Matches the follow-up patterns of the intents that continue `previous`. Slots the
+    /// follow-up leaves out are carried over from `previous` when the intent uses them.
+    pub(crate) fn recognize_follow_up(
+        &self,
+        raw_text: &str,
+        previous: &ExtractedSlots,
+    ) -> Option<ExtractedSlots> {
+        let aligned = self.intent_manager.normalizer.normalize_aligned(raw_text);
+        let mut best: Option<(&Intent, ExtractedSlots)> = None;
+
+        for intent in &self.intent_manager.intents {
+            let other_language = intent
+                .language
+                .as_ref()
+                .is_some_and(|l| *l != self.intent_manager.language());
+            if other_language || !intent.follows.contains(&previous.intent) {
+                continue;
+            }
+
+            let text = if intent.normalize {
+                aligned.text.as_str()
+            } else {
+                raw_text.trim()
+            };
+            if !intent.accepts(text) {
+                continue;
+            }
+
+            for regex in &intent.compiled_follow_ups {
+                if let Some(found) = self.slot_extractor.extract_from_pattern(
+                    regex,
+                    text,
+                    &intent.name,
+                    &intent.slots,
+                ) && best
+                    .as_ref()
+                    .is_none_or(|(_, b)| found.confidence > b.confidence)
+                {
+                    best = Some((intent, found));
+                }
+            }
+        }
+
+        let (intent, mut found) = best?;
+        if intent.normalize {
+            found.restore_original(&aligned);
+        }
+        let uses = |slot: &str| {
+            intent.slots.contains_key(slot)
+                || intent
+                    .compiled_patterns
+                    .iter()
+                    .chain(&intent.compiled_follow_ups)
+                    .any(|regex| regex.capture_names().flatten().any(|name| name == slot))
+        };
+        for (name, value) in &previous.slots {
+            if !found.slots.contains_key(name) && uses(name) {
+                found.slots.insert(name.clone(), value.clone());
+            }
+        }
+
+        found.language = self.intent_manager.language().to_string();
+        Some(found)
+    }
+
+    pub(crate) fn recognize_best(&self, text: &str) -> Option<ExtractedSlots> {
+        self.recognize(text).into_iter().next()
+    }
+
+    /// Recognizes utterances with several requests ("turn off the lights and set an alarm for
+    /// 7"), split on the conjunctions of the current language. Returns the intents in the
+    /// order they were said. The clauses win over a pattern matching the whole utterance only
+    /// when each of them matches a pattern with a higher confidence.
+    pub(crate) fn recognize_all(&self, text: &str) -> Vec<ExtractedSlots> {
+        let whole = self.recognize_with(text, false).into_iter().next();
+        let clauses = self.split_clauses(text);
+
+        if clauses.len() > 1 {
+            let intents: Vec<ExtractedSlots> = clauses
+                .iter()
+                .filter_map(|clause| self.recognize_with(clause, false).into_iter().next())
+                .collect();
+
+            let better = whole
+                .as_ref()
+                .is_none_or(|whole| intents.iter().all(|i| i.confidence > whole.confidence));
+            if intents.len() == clauses.len() && better {
+                return intents;
+            }
+        }
+
+        if let Some(whole) = whole {
+            return vec![whole];
+        }
+
+        // Nothing matched a pattern, let the classifier have a go at each clause
+        if clauses.len() > 1 {
+            let intents: Vec<ExtractedSlots> = clauses
+                .iter()
+                .filter_map(|clause| self.recognize_best(clause))
+                .collect();
+            if !intents.is_empty() {
+                return intents;
+            }
+        }
+
+        self.recognize_best(text).into_iter().collect()
+    }
+
+    fn split_clauses(&self, text: &str) -> Vec<String> {
+        let language = self.intent_manager.language();
+        let Some(conjunctions) = self.intent_manager.conjunctions.get(language) else {
+            return vec![text.to_string()];
+        };
+
+        // Longest first, so "and then" is taken as a whole before "and"
+        let mut conjunctions: Vec<Vec<String>> = conjunctions
+            .iter()
+            .map(|c| c.split_whitespace().map(str::to_lowercase).collect())
+            .filter(|c: &Vec<String>| !c.is_empty())
+            .collect();
+        conjunctions.sort_by_key(|c| std::cmp::Reverse(c.len()));
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+
+        // A connector inside a number ("vinte e um") belongs to it and doesn't split
+        let mut in_number = vec![false; words.len()];
+        let mut j = 0;
+        while j < words.len() {
+            match number_run(&lower, j, language) {
+                Some((_, end)) if end > j => {
+                    in_number[j..end].fill(true);
+                    j = end;
+                }
+                _ => j += 1,
+            }
+        }
+
+        let mut clauses = Vec::new();
+        let mut clause: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let conjunction = conjunctions
+                .iter()
+                .find(|c| lower[i..].starts_with(c.as_slice()));
+
+            match conjunction {
+                Some(conjunction) if !clause.is_empty() && !in_number[i] => {
+                    clauses.push(clause.join(" "));
+                    clause.clear();
+                    i += conjunction.len();
+                }
+                _ => {
+                    clause.push(words[i]);
+                    i += 1;
+                }
+            }
+        }
+        if !clause.is_empty() {
+            clauses.push(clause.join(" "));
+        }
+
+        clauses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intent::engine::tests::{load, matches};
+
+    #[test]
+    fn fuzzy_matching_follows_the_intent_threshold() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "fuzzy_threshold": 0.75, "patterns": ["book a flight to {city}"], "slots": {"city": ["paris", "lisbon"]}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "strict", "patterns": ["turn the lights off"]}"#,
+        );
+
+        let results = Recognizer::new(&engine).recognize_with("bok a flight to pariss", false);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].slots["city"].value, "paris");
+        assert_eq!(results[0].corrections["bok"], "book");
+        assert!(results[0].confidence < 1.0);
+
+        assert!(matches(&engine, "bake a fright to pairs").is_empty());
+        assert!(matches(&engine, "turn the light off").is_empty());
+    }
+
+    #[test]
+    fn slots_report_the_text_as_it_was_said() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "alarm", "patterns": ["set an alarm for {default/number} minutes in {city}"], "slots": {"city": "*"}}"#,
+        );
+
+        let mut found = Recognizer::new(&engine)
+            .recognize_best("Set an alarm for twenty five minutes in São  Paulo!")
+            .unwrap();
+        assert_eq!(found.get("default_number").as_int().unwrap(), 25);
+        assert_eq!(
+            found.get_raw("default_number").into_string().unwrap(),
+            "twenty five"
+        );
+        assert_eq!(found.get_raw("city").into_string().unwrap(), "São  Paulo");
+
+        let city = found.obj("city").cast::<rhai::Map>();
+        assert_eq!(city["value"].clone().into_string().unwrap(), "São Paulo");
+        assert_eq!(city["raw"].clone().into_string().unwrap(), "São  Paulo");
+        assert_eq!(city["start"].as_int().unwrap(), 40);
+        assert_eq!(city["end"].as_int().unwrap(), 50);
+        assert!(found.obj("missing").is_unit());
+    }
+
+    #[test]
+    fn follow_ups_continue_the_previous_intent() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "hotel", "patterns": ["book a hotel in {default/locations} for {default/date}"], "follow_up_patterns": ["what about {default/locations}"]}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "weather", "patterns": ["weather in {default/locations}"], "follow_up_patterns": ["and the weather"], "follows": ["hotel"]}"#,
+        );
+        let recognizer = Recognizer::new(&engine);
+        let previous = recognizer
+            .recognize_best("book a hotel in london for tomorrow")
+            .unwrap();
+
+        let hotel = recognizer
+            .recognize_follow_up("What about Paris?", &previous)
+            .unwrap();
+        assert_eq!(hotel.intent, "hotel");
+        assert!(
+            hotel.slots["default_locations"]
+                .value
+                .eq_ignore_ascii_case("paris")
+        );
+        assert_eq!(hotel.slots["default_locations"].raw, "Paris");
+        assert_eq!(
+            hotel.slots["default_date"].value,
+            previous.slots["default_date"].value
+        );
+
+        // Only the slots the weather intent uses are carried over
+        let weather = recognizer
+            .recognize_follow_up("and the weather", &previous)
+            .unwrap();
+        assert_eq!(weather.intent, "weather");
+        assert_eq!(weather.slots["default_locations"].value, "london");
+        assert!(!weather.slots.contains_key("default_date"));
+
+        // Not a follow-up of weather, nor an utterance of its own
+        assert!(
+            recognizer
+                .recognize_follow_up("what about paris", &weather)
+                .is_none()
+        );
+        assert!(matches(&engine, "what about paris").is_empty());
+    }
+
+    #[test]
+    fn the_most_specific_match_comes_first() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "song", "patterns": ["play {title}"], "slots": {"title": "*"}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "genre", "patterns": ["play {genre}"], "slots": {"genre": ["jazz", "rock"]}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "radio", "regex_patterns": ["^play (?P<station>\\w+) radio$"]}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "station", "patterns": ["play {name} radio"], "slots": {"name": "*"}}"#,
+        );
+        let recognizer = Recognizer::new(&engine);
+
+        let results = recognizer.recognize("play jazz");
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| r.intent.as_str())
+                .collect::<Vec<_>>(),
+            vec!["genre", "song"]
+        );
+        assert!(results[0].confidence > results[1].confidence);
+        assert_eq!(
+            recognizer.recognize_best("play jazz").unwrap().intent,
+            "genre"
+        );
+        assert_eq!(
+            recognizer.recognize_best("play anything").unwrap().intent,
+            "song"
+        );
+
+        // Same capture through a regex pattern scores lower than through a pattern, and both
+        // cover more of the utterance with literal text than "play {title}"
+        let results = recognizer.recognize("play jazz radio");
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| r.intent.as_str())
+                .collect::<Vec<_>>(),
+            vec!["station", "radio", "song"]
+        );
+    }
+
+    fn recognized(engine: &IntentEngine, text: &str) -> Vec<String> {
+        Recognizer::new(engine)
+            .recognize(text)
+            .into_iter()
+            .map(|m| m.intent)
+            .collect()
+    }
+
+    #[test]
+    fn classifier_is_the_fallback_above_the_threshold() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "patterns": ["book a flight to {city}"], "slots": {"city": "*"}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "weather", "patterns": ["what is the weather like"]}"#,
+        );
+
+        assert!(matches(&engine, "book me a flight").is_empty());
+        assert_eq!(recognized(&engine, "book me a flight"), vec!["flight"]);
+        assert!(recognized(&engine, "play some music").is_empty());
+
+        engine.set_classifier_threshold(0.95).unwrap();
+        assert!(recognized(&engine, "book me a flight").is_empty());
+    }
+
+    #[test]
+    fn intent_classifier_threshold_overrides_the_engine() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "classifier_threshold": 0.95, "patterns": ["book a flight to {city}"], "slots": {"city": "*"}}"#,
+        );
+
+        assert!(recognized(&engine, "book me a flight").is_empty());
+    }
+
+    #[test]
+    fn rejected_slots_skip_the_classifier() {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "patterns": ["book a flight to {city}"], "slots": {"city": ["paris", "lisbon"]}}"#,
+        );
+
+        assert_eq!(
+            recognized(&engine, "book a flight to paris"),
+            vec!["flight"]
+        );
+        assert!(recognized(&engine, "book a flight to atlantis").is_empty());
+    }
+
+    #[test]
+    fn classifier_threshold_must_be_a_similarity() {
+        let mut engine = IntentEngine::new();
+        assert!(engine.set_classifier_threshold(1.5).is_err());
+        assert!(engine.set_classifier_threshold(-0.1).is_err());
+        assert!(engine.set_classifier_threshold(1.0).is_ok());
+        assert_eq!(engine.classifier_threshold, 1.0);
+    }
+
+    fn clauses(engine: &IntentEngine, text: &str) -> Vec<String> {
+        Recognizer::new(engine).split_clauses(text)
+    }
+
+    #[test]
+    fn splits_on_the_language_conjunctions() {
+        let mut engine = IntentEngine::new();
+        assert_eq!(
+            clauses(&engine, "turn off the lights and then play music"),
+            vec!["turn off the lights", "play music"]
+        );
+        assert_eq!(clauses(&engine, "rock e roll"), vec!["rock e roll"]);
+
+        engine.set_language("pt");
+        assert_eq!(
+            clauses(&engine, "apaga a luz e depois toca música"),
+            vec!["apaga a luz", "toca música"]
+        );
+
+        engine.set_conjunctions("pt", vec!["mais".to_string()]);
+        assert_eq!(
+            clauses(&engine, "apaga a luz mais toca música"),
+            vec!["apaga a luz", "toca música"]
+        );
+        assert_eq!(clauses(&engine, "apaga a luz e toca música").len(), 1);
+    }
+
+    #[test]
+    fn does_not_split_inside_spelled_out_numbers() {
+        let mut engine = IntentEngine::new();
+        engine.set_language("pt");
+        load(
+            &mut engine,
+            r#"{"intent": "temperature", "patterns": ["põe a temperatura em {default/number} graus"]}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "lights", "patterns": ["apaga a luz"]}"#,
+        );
+
+        assert_eq!(
+            clauses(
+                &engine,
+                "põe a temperatura em vinte e um graus e apaga a luz"
+            ),
+            vec!["põe a temperatura em vinte e um graus", "apaga a luz"]
+        );
+
+        let results = Recognizer::new(&engine)
+            .recognize_all("põe a temperatura em vinte e um graus e apaga a luz");
+        let intents: Vec<&str> = results.iter().map(|r| r.intent.as_str()).collect();
+        assert_eq!(intents, vec!["temperature", "lights"]);
+        assert_eq!(results[0].slots["default_number"].value, "21");
+    }
+
+    fn partial(engine: &IntentEngine, text: &str) -> Vec<(String, MatchState)> {
+        Recognizer::new(engine)
+            .recognize_partial(text)
+            .matches
+            .into_iter()
+            .map(|m| (m.intent.intent, m.state))
+            .collect()
+    }
+
+    fn flights() -> IntentEngine {
+        let mut engine = IntentEngine::new();
+        load(
+            &mut engine,
+            r#"{"intent": "flight", "patterns": ["book a flight", "book a flight to {city}"], "slots": {"city": "*"}}"#,
+        );
+        load(
+            &mut engine,
+            r#"{"intent": "hotel", "patterns": ["book a hotel"]}"#,
+        );
+        engine
+    }
+
+    #[test]
+    fn partial_recognition_lists_complete_and_unfinished_intents() {
+        let engine = flights();
+        use MatchState::*;
+
+        assert_eq!(
+            partial(&engine, "book a "),
+            vec![
+                ("flight".to_string(), NeedsMore),
+                ("hotel".to_string(), NeedsMore)
+            ]
+        );
+        assert_eq!(
+            partial(&engine, "book a ho"),
+            vec![("hotel".to_string(), NeedsMore)]
+        );
+        assert_eq!(
+            partial(&engine, "book a hotel "),
+            vec![("hotel".to_string(), Complete)]
+        );
+        assert_eq!(
+            partial(&engine, "book a flight "),
+            vec![
+                ("flight".to_string(), Complete),
+                ("flight".to_string(), NeedsMore)
+            ]
+        );
+        assert!(partial(&engine, "cook a ").is_empty());
+    }
+
+    #[test]
+    fn unambiguous_waits_for_longer_patterns() {
+        let engine = flights();
+        let recognizer = Recognizer::new(&engine);
+        let settled = |text| {
+            recognizer
+                .recognize_partial(text)
+                .unambiguous()
+                .map(|m| m.intent.clone())
+        };
+
+        assert_eq!(settled("book a "), None);
+        assert_eq!(settled("book a hotel ").as_deref(), Some("hotel"));
+        assert_eq!(settled("book a flight "), None);
+        assert_eq!(settled("book a flight to paris "), None);
+    }
 }
@@ -1,3 +1,6 @@
+use crate::intent::entities;
+use crate::intent::processor::ScriptProcessor;
+use regex::Regex;
 use serde::Serialize;
 use std::collections::HashMap;
 
@@ -15,6 +18,23 @@ pub enum SlotDefinition {
         #[serde(skip)]
         processor: fn(String) -> Option<String>,
     },
+
+    /// Processed by an AviScript function of the skill
+    #[serde(rename = "catch_script")]
+    CatchScript {
+        #[serde(skip)]
+        processor: ScriptProcessor,
+    },
+
+    /// A skill-defined slot type from its `entities/` directory
+    #[serde(rename = "entity")]
+    Entity {
+        /// Every value and synonym, lowercased, to its canonical value
+        synonyms: HashMap<String, String>,
+        /// Anchored regexes accepting values that are not listed, kept as written
+        #[serde(skip)]
+        validators: Vec<Regex>,
+    },
 }
 
 impl SlotDefinition {
@@ -29,6 +49,28 @@ impl SlotDefinition {
     pub(crate) fn new_processor(processor: fn(String) -> Option<String>) -> Self {
         SlotDefinition::CatchProcess { processor }
     }
+
+    pub(crate) fn new_script(processor: ScriptProcessor) -> Self {
+        SlotDefinition::CatchScript { processor }
+    }
+
+    pub(crate) fn new_entity(synonyms: HashMap<String, String>, validators: Vec<Regex>) -> Self {
+        SlotDefinition::Entity {
+            synonyms,
+            validators,
+        }
+    }
+
+    /// Same names as the serialized `type` tag.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            SlotDefinition::Enumeration { .. } => "enumeration",
+            SlotDefinition::CatchAll => "catch_all",
+            SlotDefinition::CatchProcess { .. } => "catch_process",
+            SlotDefinition::CatchScript { .. } => "catch_script",
+            SlotDefinition::Entity { .. } => "entity",
+        }
+    }
 }
 
 pub struct DefaultSlotManager {
@@ -49,12 +91,34 @@ impl DefaultSlotManager {
             ]),
         );
 
+        defaults.insert(
+            "number".to_string(),
+            SlotDefinition::new_processor(entities::parse_number),
+        );
+        defaults.insert(
+            "ordinal".to_string(),
+            SlotDefinition::new_processor(entities::parse_ordinal),
+        );
+        defaults.insert(
+            "percentage".to_string(),
+            SlotDefinition::new_processor(entities::parse_percentage),
+        );
+        defaults.insert(
+            "duration".to_string(),
+            SlotDefinition::new_processor(entities::parse_duration),
+        );
+        defaults.insert(
+            "time".to_string(),
+            SlotDefinition::new_processor(entities::parse_time),
+        );
+        defaults.insert(
+            "date".to_string(),
+            SlotDefinition::new_processor(entities::parse_date),
+        );
+        // Kept for skills written before `date` existed
         defaults.insert(
             "dates".to_string(),
-            SlotDefinition::new_processor(|date| {
-                println!("{}", date);
-                Option::from(date)
-            }),
+            SlotDefinition::new_processor(entities::parse_date),
         );
 
         DefaultSlotManager { defaults }
@@ -1,12 +1,70 @@
+use crate::intent::entities;
+use crate::intent::fuzzy;
+use crate::intent::normalizer::Normalized;
+use crate::intent::partial;
 use crate::intent::slot::{DefaultSlotManager, SlotDefinition};
+use crate::intent::template;
 use regex::{Regex, RegexBuilder};
-use rhai::{Array, Dynamic, Map};
+use rhai::{Array, Dynamic, EvalAltResult, Map};
 use std::collections::HashMap;
+use std::error::Error;
+
+/// Weight applied to matches coming from `patterns`.
+const PATTERN_ORIGIN_WEIGHT: f64 = 1.0;
+/// Weight applied to matches coming from `regex_patterns`, which are unanchored and less curated.
+const REGEX_ORIGIN_WEIGHT: f64 = 0.9;
+
+/// Share of the confidence given to literal-token coverage, the rest goes to slot specificity.
+const COVERAGE_WEIGHT: f64 = 0.6;
+
+/// A filled slot, as found in the utterance the recognizer matched against.
+#[derive(Debug, Clone)]
+pub struct SlotValue {
+    /// The text of the utterance the slot covered
+    pub(crate) raw: String,
+    /// The value after validation and processing, e.g. an ISO-8601 date for `default/date`
+    pub(crate) value: String,
+    /// Built-in entity name (`number`, `date`, `locations`...) or the kind of intent slot
+    pub(crate) entity: String,
+    /// Character offsets of `raw` in the utterance
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl SlotValue {
+    /// The value converted to the type of its entity: numbers become ints or floats, dates,
+    /// times and durations become maps, anything else stays a string.
+    pub(crate) fn typed(&self) -> Dynamic {
+        entities::typed_value(&self.entity, &self.value)
+            .unwrap_or_else(|| self.value.clone().into())
+    }
+
+    /// Points a slot found in a normalized utterance back at the text the user wrote.
+    pub(crate) fn restore_original(&mut self, normalized: &Normalized) {
+        (self.start, self.end) = normalized.original_span(self.start, self.end);
+        self.raw = normalized.original_text(self.start, self.end);
+    }
+
+    pub(crate) fn to_map(&self) -> Map {
+        let mut map = Map::new();
+        map.insert("raw".into(), self.raw.clone().into());
+        map.insert("value".into(), self.typed());
+        map.insert("normalized".into(), self.value.clone().into());
+        map.insert("entity".into(), self.entity.clone().into());
+        map.insert("start".into(), (self.start as i64).into());
+        map.insert("end".into(), (self.end as i64).into());
+        map
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ExtractedSlots {
     pub(crate) intent: String,
-    pub(crate) slots: HashMap<String, String>,
+    pub(crate) slots: HashMap<String, SlotValue>,
+    pub(crate) confidence: f64,
+    pub(crate) corrections: HashMap<String, String>,
+    /// Language the utterance was recognized in, filled in by the recognizer
+    pub(crate) language: String,
 }
 
 impl ExtractedSlots {
@@ -14,23 +72,46 @@ impl ExtractedSlots {
         self.intent.clone()
     }
 
-    pub(crate) fn get_slots(&mut self) -> HashMap<String, String> {
-        self.slots.clone()
+    pub(crate) fn get_confidence(&mut self) -> f64 {
+        self.confidence
+    }
+
+    pub(crate) fn get_language(&mut self) -> String {
+        self.language.clone()
+    }
+
+    pub(crate) fn get_corrections(&mut self) -> Map {
+        let mut map = Map::new();
+        for (k, v) in &self.corrections {
+            map.insert(k.into(), v.clone().into());
+        }
+        map
+    }
+
+    pub(crate) fn get_slots(&mut self) -> Map {
+        self.all()
     }
 
     pub(crate) fn get(&mut self, slot: &str) -> Dynamic {
-        self.slots.get(slot).cloned().unwrap_or_default().into()
+        self.slots
+            .get(slot)
+            .map(SlotValue::typed)
+            .unwrap_or_else(|| String::new().into())
     }
 
     pub(crate) fn get_raw(&mut self, slot: &str) -> Dynamic {
-        self.get(slot) // For now behaves same as get; can differ if raw format needed
+        self.slots
+            .get(slot)
+            .map(|v| v.raw.clone())
+            .unwrap_or_default()
+            .into()
     }
 
-    pub(crate) fn require(&mut self, slot: &str) -> Dynamic {
+    pub(crate) fn require(&mut self, slot: &str) -> Result<Dynamic, Box<EvalAltResult>> {
         self.slots
             .get(slot)
-            .map(|v| v.clone().into())
-            .unwrap_or_else(|| panic!("Required slot '{}' not found", slot))
+            .map(SlotValue::typed)
+            .ok_or_else(|| format!("Required slot '{}' not found", slot).into())
     }
 
     pub(crate) fn optional(&mut self, slot: &str) -> Dynamic {
@@ -42,25 +123,35 @@ impl ExtractedSlots {
     }
 
     pub(crate) fn equal(&mut self, slot: &str, value: &str) -> bool {
-        self.slots.get(slot).is_some_and(|v| v == value)
+        self.slots.get(slot).is_some_and(|v| v.value == value)
     }
 
     pub(crate) fn in_list(&mut self, slot: &str, list: Array) -> bool {
         match self.slots.get(slot) {
-            Some(value) => list.iter().any(|item| item.to_string() == *value),
+            Some(slot) => list.iter().any(|item| item.to_string() == slot.value),
             None => false,
         }
     }
 
     pub(crate) fn in_dict(&mut self, slot: &str, map: Map) -> bool {
         match self.slots.get(slot) {
-            Some(value) => map.contains_key(value.as_str()),
+            Some(slot) => map.contains_key(slot.value.as_str()),
             None => false,
         }
     }
 
     pub(crate) fn obj(&mut self, slot: &str) -> Dynamic {
-        self.get(slot) // Can be enhanced to return structured data
+        self.slots
+            .get(slot)
+            .map(|v| v.to_map().into())
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    /// Points slots found in a normalized utterance back at the text the user wrote.
+    pub(crate) fn restore_original(&mut self, normalized: &Normalized) {
+        for slot in self.slots.values_mut() {
+            slot.restore_original(normalized);
+        }
     }
 
     pub(crate) fn count(&mut self) -> i64 {
@@ -70,7 +161,7 @@ impl ExtractedSlots {
     pub(crate) fn all(&mut self) -> Map {
         let mut map = Map::new();
         for (k, v) in &self.slots {
-            map.insert(k.into(), v.clone().into());
+            map.insert(k.into(), v.typed());
         }
         map
     }
@@ -78,19 +169,19 @@ impl ExtractedSlots {
     pub(crate) fn match_pattern(&mut self, slot: &str, pattern: &str) -> bool {
         use regex::Regex;
         match self.slots.get(slot) {
-            Some(value) => Regex::new(pattern).is_ok_and(|re| re.is_match(value)),
+            Some(slot) => Regex::new(pattern).is_ok_and(|re| re.is_match(&slot.value)),
             None => false,
         }
     }
 
     pub(crate) fn is_type(&mut self, slot: &str, type_name: &str) -> bool {
         match self.slots.get(slot) {
-            Some(value) => match type_name {
-                "int" => value.parse::<i64>().is_ok(),
-                "float" => value.parse::<f64>().is_ok(),
-                "bool" => value.parse::<bool>().is_ok(),
+            Some(slot) => match type_name {
+                "int" => slot.value.parse::<i64>().is_ok(),
+                "float" => slot.value.parse::<f64>().is_ok(),
+                "bool" => slot.value.parse::<bool>().is_ok(),
                 "string" => true,
-                _ => false,
+                entity => slot.entity == entity,
             },
             None => false,
         }
@@ -99,11 +190,99 @@ impl ExtractedSlots {
 
 pub struct SlotExtractor<'a> {
     default_slots: &'a DefaultSlotManager,
+    entities: &'a HashMap<String, SlotDefinition>,
 }
 
 impl<'a> SlotExtractor<'a> {
-    pub(crate) fn new(default_slots: &'a DefaultSlotManager) -> Self {
-        SlotExtractor { default_slots }
+    pub(crate) fn new(
+        default_slots: &'a DefaultSlotManager,
+        entities: &'a HashMap<String, SlotDefinition>,
+    ) -> Self {
+        SlotExtractor {
+            default_slots,
+            entities,
+        }
+    }
+
+    /// Finds the definition of a slot. `default/name` and `entity/name` slots are shared by
+    /// every intent and are returned with their name; `separator` is `/` in patterns and `_`
+    /// in capture group names.
+    fn lookup<'s>(
+        &'s self,
+        slot_name: &'s str,
+        separator: char,
+        intent_slots: &'s HashMap<String, SlotDefinition>,
+    ) -> (Option<&'s str>, Option<&'s SlotDefinition>) {
+        let shared = |prefix: &str| {
+            slot_name
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_prefix(separator))
+        };
+
+        if let Some(name) = shared("default") {
+            (Some(name), self.default_slots.get(name))
+        } else if let Some(name) = shared("entity") {
+            (Some(name), self.entities.get(name))
+        } else {
+            (None, intent_slots.get(slot_name))
+        }
+    }
+
+    /// How much a slot of this kind narrows down the match, from 0 (anything) to 1 (fixed values).
+    fn slot_specificity(defn: Option<&SlotDefinition>) -> f64 {
+        match defn {
+            Some(SlotDefinition::Enumeration { .. }) | Some(SlotDefinition::Entity { .. }) => 1.0,
+            Some(SlotDefinition::CatchProcess { .. })
+            | Some(SlotDefinition::CatchScript { .. }) => 0.7,
+            Some(SlotDefinition::CatchAll) | None => 0.4,
+        }
+    }
+
+    /// Scores a match from the share of the utterance covered by literal text, the
+    /// specificity of the slots that were filled and the origin of the pattern.
+    fn score(text: &str, captured_len: usize, specificities: &[f64], origin_weight: f64) -> f64 {
+        let text_len = text.chars().count();
+        let coverage = if text_len == 0 {
+            1.0
+        } else {
+            text_len.saturating_sub(captured_len) as f64 / text_len as f64
+        };
+
+        let specificity = if specificities.is_empty() {
+            1.0
+        } else {
+            specificities.iter().sum::<f64>() / specificities.len() as f64
+        };
+
+        origin_weight * (COVERAGE_WEIGHT * coverage + (1.0 - COVERAGE_WEIGHT) * specificity)
+    }
+
+    /// Entity reported for a slot: the shared name for `default/` and `entity/` slots, else
+    /// its kind.
+    fn entity_type(shared_name: Option<&str>, defn: Option<&SlotDefinition>) -> String {
+        match (shared_name, defn) {
+            (Some(shared_name), _) => shared_name.to_string(),
+            (None, Some(defn)) => defn.kind().to_string(),
+            (None, None) => SlotDefinition::CatchAll.kind().to_string(),
+        }
+    }
+
+    fn char_offset(text: &str, byte_offset: usize) -> usize {
+        text[..byte_offset].chars().count()
+    }
+
+    /// Character span of every word of the utterance, `words` being slices of `text`.
+    fn word_spans(text: &str, words: &[&str]) -> Vec<(usize, usize)> {
+        words
+            .iter()
+            .map(|word| {
+                let byte_start = word.as_ptr() as usize - text.as_ptr() as usize;
+                (
+                    Self::char_offset(text, byte_start),
+                    Self::char_offset(text, byte_start + word.len()),
+                )
+            })
+            .collect()
     }
 
     fn validate_and_process_slot(&self, val_text: String, defn: &SlotDefinition) -> Option<String> {
@@ -117,151 +296,428 @@ impl<'a> SlotExtractor<'a> {
             }
             SlotDefinition::CatchAll => Some(val_text),
             SlotDefinition::CatchProcess { processor } => processor(val_text),
+            SlotDefinition::CatchScript { processor } => processor.process(val_text),
+            SlotDefinition::Entity {
+                synonyms,
+                validators,
+            } => match synonyms.get(&val_text.to_lowercase()) {
+                Some(canonical) => Some(canonical.clone()),
+                None if validators.iter().any(|v| v.is_match(&val_text)) => Some(val_text),
+                None => None,
+            },
         }
     }
 
     pub(crate) fn extract_from_pattern(
         &self,
-        pattern: &str,
+        regex: &Regex,
         text: &str,
         intent_name: &str,
         intent_slots: &HashMap<String, SlotDefinition>,
     ) -> Option<ExtractedSlots> {
-        let regex = self.pattern_to_regex(pattern)?;
         let captures = regex.captures(text)?;
 
         let mut slots = HashMap::new();
+        let mut captured_len = 0;
+        let mut specificities = Vec::new();
 
         for name in regex.capture_names().flatten() {
             if let Some(val) = captures.name(name) {
-                let mut val_text = val.as_str().to_string();
-                let defn;
+                let (shared_name, defn) = self.lookup(name, '_', intent_slots);
+                let defn = defn?;
+
+                captured_len += val.as_str().chars().count();
+                specificities.push(Self::slot_specificity(Some(defn)));
+
+                let value = self.validate_and_process_slot(val.as_str().to_string(), defn)?;
+
+                slots.insert(
+                    name.to_string(),
+                    SlotValue {
+                        raw: val.as_str().to_string(),
+                        value,
+                        entity: Self::entity_type(shared_name, Some(defn)),
+                        start: Self::char_offset(text, val.start()),
+                        end: Self::char_offset(text, val.end()),
+                    },
+                );
+            }
+        }
 
-                if name.starts_with("default_") {
-                    let default_name = &name[8..]; // Skip "default_"
-                    defn = self.default_slots.get(default_name)?;
-                } else {
-                    defn = intent_slots.get(name)?;
+        Some(ExtractedSlots {
+            intent: intent_name.to_string(),
+            slots,
+            confidence: Self::score(text, captured_len, &specificities, PATTERN_ORIGIN_WEIGHT),
+            corrections: HashMap::new(),
+            language: String::new(),
+        })
+    }
+
+    /// Typo-tolerant version of `extract_from_pattern`, tried on every phrasing of the pattern
+    /// (see `template::expand`) and keeping the best. Literal words and enumeration values only
+    /// need a similarity of at least `threshold`, the corrected words are reported in
+    /// `ExtractedSlots::corrections`.
+    pub(crate) fn extract_fuzzy_from_pattern(
+        &self,
+        variants: &[String],
+        text: &str,
+        intent_name: &str,
+        intent_slots: &HashMap<String, SlotDefinition>,
+        threshold: f64,
+    ) -> Option<ExtractedSlots> {
+        variants
+            .iter()
+            .filter_map(|variant| {
+                self.extract_fuzzy_from_variant(variant, text, intent_name, intent_slots, threshold)
+            })
+            .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+    }
+
+    fn extract_fuzzy_from_variant(
+        &self,
+        pattern: &str,
+        text: &str,
+        intent_name: &str,
+        intent_slots: &HashMap<String, SlotDefinition>,
+        threshold: f64,
+    ) -> Option<ExtractedSlots> {
+        let tokens = fuzzy::tokenize_pattern(pattern);
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let resolve = |name: &str, candidate: &str| {
+            let defn = self.lookup(name, '/', intent_slots).1?;
+
+            match defn {
+                SlotDefinition::Enumeration { values } => values
+                    .iter()
+                    .map(|v| (v.clone(), fuzzy::similarity(v, candidate)))
+                    .filter(|(_, score)| *score >= threshold)
+                    .max_by(|a, b| a.1.total_cmp(&b.1)),
+                SlotDefinition::Entity { synonyms, .. } => synonyms
+                    .iter()
+                    .map(|(synonym, canonical)| {
+                        (canonical.clone(), fuzzy::similarity(synonym, candidate))
+                    })
+                    .filter(|(_, score)| *score >= threshold)
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .or_else(|| {
+                        self.validate_and_process_slot(candidate.to_string(), defn)
+                            .map(|v| (v, 1.0))
+                    }),
+                _ => self
+                    .validate_and_process_slot(candidate.to_string(), defn)
+                    .map(|v| (v, 1.0)),
+            }
+        };
+
+        let found = fuzzy::align(&tokens, &words, 0, threshold, &resolve)?;
+
+        let word_spans = Self::word_spans(text, &words);
+
+        let mut slots = HashMap::new();
+        let mut captured_len = 0;
+        let mut specificities = Vec::new();
+
+        for slot in found.slots.iter().rev() {
+            let (shared_name, defn) = self.lookup(&slot.name, '/', intent_slots);
+
+            captured_len += slot.raw.chars().count();
+            specificities.push(Self::slot_specificity(defn));
+            slots.insert(
+                slot.name.replacen('/', "_", 1),
+                SlotValue {
+                    raw: slot.raw.clone(),
+                    value: slot.value.clone(),
+                    entity: Self::entity_type(shared_name, defn),
+                    start: word_spans[slot.words.start].0,
+                    end: word_spans[slot.words.end - 1].1,
+                },
+            );
+        }
+
+        let confidence = Self::score(text, captured_len, &specificities, PATTERN_ORIGIN_WEIGHT)
+            * found.similarity();
+
+        Some(ExtractedSlots {
+            intent: intent_name.to_string(),
+            slots,
+            confidence,
+            corrections: found.corrections,
+            language: String::new(),
+        })
+    }
+
+    /// Whether a `default/name` or `entity/name` slot exists.
+    pub(crate) fn has_shared_slot(&self, slot_name: &str) -> bool {
+        self.lookup(slot_name, '/', &HashMap::new()).1.is_some()
+    }
+
+    /// Value of `slot_name` in the answer to a question asking for it. The whole answer is
+    /// tried first, then its word sequences, longest first, so "to paris" fills a city.
+    pub(crate) fn extract_answer(
+        &self,
+        slot_name: &str,
+        text: &str,
+        intent_slots: &HashMap<String, SlotDefinition>,
+    ) -> Option<SlotValue> {
+        let (shared_name, defn) = self.lookup(slot_name, '/', intent_slots);
+        let defn = defn?;
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let word_spans = Self::word_spans(text, &words);
+
+        for len in (1..=words.len()).rev() {
+            for start in 0..=words.len() - len {
+                let raw = words[start..start + len].join(" ");
+                if let Some(value) = self.validate_and_process_slot(raw.clone(), defn) {
+                    return Some(SlotValue {
+                        raw,
+                        value,
+                        entity: Self::entity_type(shared_name, Some(defn)),
+                        start: word_spans[start].0,
+                        end: word_spans[start + len - 1].1,
+                    });
                 }
+            }
+        }
+
+        None
+    }
 
-                let processed = self.validate_and_process_slot(val.as_str().to_string(), defn);
-                processed.as_ref()?;
-                val_text = processed.unwrap().as_str().to_string();
+    /// For an utterance that is still being said: the slots settled so far if `text` is the
+    /// beginning of a phrasing of the pattern. The confidence is the share of the pattern
+    /// already covered.
+    pub(crate) fn extract_prefix_from_variant(
+        &self,
+        pattern: &str,
+        text: &str,
+        intent_name: &str,
+        intent_slots: &HashMap<String, SlotDefinition>,
+        last_word_partial: bool,
+    ) -> Option<ExtractedSlots> {
+        let tokens = fuzzy::tokenize_pattern(pattern);
+        let words: Vec<&str> = text.split_whitespace().collect();
 
-                slots.insert(name.to_string(), val_text);
+        let resolve = |name: &str, candidate: &str, open: bool| {
+            let defn = self.lookup(name, '/', intent_slots).1?;
+            if !open {
+                return self.validate_and_process_slot(candidate.to_string(), defn);
             }
+
+            // An unfinished value only has to be the start of an accepted one
+            let candidate = candidate.to_lowercase();
+            let accepted = match defn {
+                SlotDefinition::Enumeration { values } => values
+                    .iter()
+                    .any(|v| v.to_lowercase().starts_with(&candidate)),
+                SlotDefinition::Entity {
+                    synonyms,
+                    validators,
+                } => !validators.is_empty() || synonyms.keys().any(|s| s.starts_with(&candidate)),
+                _ => true,
+            };
+            accepted.then(String::new)
+        };
+
+        let found = partial::prefix_align(&tokens, &words, 0, last_word_partial, &resolve)?;
+        let word_spans = Self::word_spans(text, &words);
+
+        let mut slots = HashMap::new();
+        for slot in &found.slots {
+            let (shared_name, defn) = self.lookup(&slot.name, '/', intent_slots);
+            slots.insert(
+                slot.name.replacen('/', "_", 1),
+                SlotValue {
+                    raw: slot.raw.clone(),
+                    value: slot.value.clone(),
+                    entity: Self::entity_type(shared_name, defn),
+                    start: word_spans[slot.words.start].0,
+                    end: word_spans[slot.words.end - 1].1,
+                },
+            );
         }
 
         Some(ExtractedSlots {
             intent: intent_name.to_string(),
             slots,
+            confidence: found.matched_tokens as f64 / tokens.len() as f64,
+            corrections: HashMap::new(),
+            language: String::new(),
         })
     }
 
     pub(crate) fn extract_from_regex(
         &self,
-        regex_pattern: &str,
+        regex: &Regex,
         text: &str,
         intent_name: &str,
         intent_slots: &HashMap<String, SlotDefinition>,
     ) -> Option<ExtractedSlots> {
-        let regex = RegexBuilder::new(regex_pattern)
-            .case_insensitive(true)
-            .build()
-            .ok()?;
-
         let captures = regex.captures(text)?;
 
         let mut slots = HashMap::new();
+        let mut captured_len = 0;
+        let mut specificities = Vec::new();
 
         for name in regex.capture_names().flatten() {
             if let Some(val) = captures.name(name) {
-                let mut val_text = val.as_str().to_string();
+                let defn = intent_slots.get(name);
 
-                // Intent-specific slot
-                if let Some(defn) = intent_slots.get(name) {
-                    let processed = self.validate_and_process_slot(val.as_str().to_string(), defn);
-                    processed.as_ref()?;
-                    val_text = processed.unwrap().as_str().to_string();
-                }
+                captured_len += val.as_str().chars().count();
+                specificities.push(Self::slot_specificity(defn));
 
-                slots.insert(name.to_string(), val_text);
+                // Intent-specific slot
+                let value = match defn {
+                    Some(defn) => self.validate_and_process_slot(val.as_str().to_string(), defn)?,
+                    None => val.as_str().to_string(),
+                };
+
+                slots.insert(
+                    name.to_string(),
+                    SlotValue {
+                        raw: val.as_str().to_string(),
+                        value,
+                        entity: Self::entity_type(None, defn),
+                        start: Self::char_offset(text, val.start()),
+                        end: Self::char_offset(text, val.end()),
+                    },
+                );
             }
         }
 
+        // Text outside the (unanchored) match counts as uncovered, just like slot text.
+        let unmatched_len = text.chars().count() - captures[0].chars().count();
+
         Some(ExtractedSlots {
             intent: intent_name.to_string(),
             slots,
+            confidence: Self::score(
+                text,
+                captured_len + unmatched_len,
+                &specificities,
+                REGEX_ORIGIN_WEIGHT,
+            ),
+            corrections: HashMap::new(),
+            language: String::new(),
         })
     }
 
-    fn pattern_to_regex(&self, pattern: &str) -> Option<Regex> {
-        let mut regex_str = "^".to_string();
-        let mut current_pos = 0;
+    pub(crate) fn compile_regex(regex_pattern: &str) -> Result<Regex, regex::Error> {
+        RegexBuilder::new(regex_pattern)
+            .case_insensitive(true)
+            .build()
+    }
 
-        // Simple pattern tokenizer
-        let chars: Vec<char> = pattern.chars().collect();
+    pub(crate) fn pattern_to_regex(&self, pattern: &str) -> Result<Regex, Box<dyn Error>> {
+        let nodes = template::parse(pattern)?;
+        let body = template::to_regex(&nodes, &|slot_name| self.slot_to_regex(slot_name))?;
 
-        while current_pos < chars.len() {
-            if chars[current_pos] == '{' {
-                // Find the closing brace
-                let start_pos = current_pos;
-                current_pos += 1;
+        Ok(Self::compile_regex(&format!("^{}$", body))?)
+    }
 
-                while current_pos < chars.len() && chars[current_pos] != '}' {
-                    current_pos += 1;
-                }
+    fn slot_to_regex(&self, slot_name: &str) -> Result<String, Box<dyn Error>> {
+        if !slot_name.starts_with("default/") && !slot_name.starts_with("entity/") {
+            // Intent-specific slot
+            return Ok(format!("(?P<{}>.+?)", slot_name));
+        }
 
-                if current_pos >= chars.len() {
-                    // Unclosed brace, treat as literal
-                    regex_str.push_str(&regex::escape(&pattern[start_pos..current_pos]));
-                } else {
-                    // Extract slot name
-                    let slot_name = &pattern[(start_pos + 1)..current_pos];
-
-                    if slot_name.starts_with("default/") {
-                        let parts: Vec<&str> = slot_name.split('/').collect();
-                        if parts.len() != 2 {
-                            return None;
-                        }
-
-                        let default_name = parts[1];
-                        let defn = self.default_slots.get(default_name)?;
-
-                        // Use default_ prefix in the capture group to distinguish it
-                        let group_name = format!("default_{}", default_name);
-
-                        match defn {
-                            SlotDefinition::Enumeration { values } => {
-                                let alt = values.join("|");
-                                regex_str.push_str(&format!("(?P<{}>{})", &group_name, alt));
-                            }
-                            SlotDefinition::CatchAll => {
-                                regex_str.push_str(&format!("(?P<{}>.+?)", &group_name));
-                            }
-                            SlotDefinition::CatchProcess { .. } => {
-                                regex_str.push_str(&format!("(?P<{}>.+?)", &group_name));
-                            }
-                        }
-                    } else {
-                        // Intent-specific slot
-                        regex_str.push_str(&format!("(?P<{}>.+?)", slot_name));
-                    }
-
-                    current_pos += 1; // Skip closing brace
-                }
-            } else {
-                // Regular character
-                regex_str.push_str(&regex::escape(&chars[current_pos].to_string()));
-                current_pos += 1;
-            }
+        let parts: Vec<&str> = slot_name.split('/').collect();
+        if parts.len() != 2 {
+            return Err(format!("Invalid shared slot {{{}}}", slot_name).into());
         }
 
-        regex_str.push('$');
+        let (kind, shared_name) = (parts[0], parts[1]);
+        let defn = match kind {
+            "default" => self.default_slots.get(shared_name),
+            _ => self.entities.get(shared_name),
+        }
+        .ok_or_else(|| format!("Unknown {} slot {{{}}}", kind, slot_name))?;
 
-        RegexBuilder::new(&regex_str)
-            .case_insensitive(true)
-            .build()
-            .ok()
+        // Use default_/entity_ prefix in the capture group to distinguish it
+        let group_name = format!("{}_{}", kind, shared_name);
+
+        let regex = match defn {
+            SlotDefinition::Enumeration { values } => {
+                let alt = values
+                    .iter()
+                    .map(|v| regex::escape(v))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                format!("(?P<{}>{})", &group_name, alt)
+            }
+            SlotDefinition::CatchAll => format!("(?P<{}>.+?)", &group_name),
+            SlotDefinition::CatchProcess { .. } | SlotDefinition::CatchScript { .. } => {
+                format!("(?P<{}>.+?)", &group_name)
+            }
+            // Values outside the list are up to the validators, which run on the capture
+            SlotDefinition::Entity { validators, .. } if !validators.is_empty() => {
+                format!("(?P<{}>.+?)", &group_name)
+            }
+            SlotDefinition::Entity { synonyms, .. } => {
+                // Longest first, so "new york city" wins over "new york"
+                let mut keys: Vec<&String> = synonyms.keys().collect();
+                keys.sort_by_key(|k| std::cmp::Reverse(k.chars().count()));
+                let alt = keys
+                    .iter()
+                    .map(|k| regex::escape(k))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                format!("(?P<{}>{})", &group_name, alt)
+            }
+        };
+
+        Ok(regex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(text: &str, last_word_partial: bool) -> Option<ExtractedSlots> {
+        let defaults = DefaultSlotManager::new();
+        let entities = HashMap::new();
+        let mut intent_slots = HashMap::new();
+        intent_slots.insert(
+            "class".to_string(),
+            SlotDefinition::new_enumeration(vec!["economy".to_string(), "business".to_string()]),
+        );
+
+        SlotExtractor::new(&defaults, &entities).extract_prefix_from_variant(
+            "fly to {default/locations} in {class} class",
+            text,
+            "flight",
+            &intent_slots,
+            last_word_partial,
+        )
+    }
+
+    #[test]
+    fn prefix_settles_slots_followed_by_more_of_the_pattern() {
+        let found = prefix("fly to new york in", false).unwrap();
+        let city = &found.slots["default_locations"];
+        assert_eq!(city.value, "new york");
+        assert_eq!((city.start, city.end), (7, 15));
+        assert_eq!(found.confidence, 4.0 / 6.0);
+
+        // Still being said, so not settled
+        let found = prefix("fly to new", false).unwrap();
+        assert!(found.slots.is_empty());
+        assert_eq!(found.confidence, 2.0 / 6.0);
+    }
+
+    #[test]
+    fn prefix_accepts_unfinished_words_and_values() {
+        assert!(prefix("fly t", true).is_some());
+        assert!(prefix("fly t", false).is_none());
+        assert!(prefix("fly to paris in bus", false).is_some());
+        assert!(prefix("fly to paris in first", false).is_none());
+        assert!(prefix("fly to atlantis in", false).is_none());
+    }
+
+    #[test]
+    fn complete_or_longer_text_is_not_a_prefix() {
+        assert!(prefix("fly to paris in economy class", false).is_none());
+        assert!(prefix("fly to paris in economy class now", false).is_none());
     }
 }
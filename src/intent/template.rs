@@ -0,0 +1,309 @@
+use std::error::Error;
+
+/// Caps how many concrete phrasings a single template expands to for fuzzy matching.
+const MAX_VARIANTS: usize = 256;
+
+/// Characters with a meaning in pattern templates, `\` escapes them.
+pub(crate) const TEMPLATE_CHARS: [char; 8] = ['{', '}', '[', ']', '(', ')', '|', '*'];
+
+/// A parsed `.intent` pattern:
+/// - `{slot}` and `{default/name}` capture a slot
+/// - `[words]` is optional, `[a|b]` optionally matches one of the alternatives
+/// - `(a|b|c)` matches one of the alternatives, an empty alternative makes the group optional
+/// - `*` matches one or more whole words
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Text(String),
+    Space,
+    Slot(String),
+    Optional(Vec<Node>),
+    Choice(Vec<Vec<Node>>),
+    Wildcard,
+}
+
+pub(crate) fn parse(pattern: &str) -> Result<Vec<Node>, Box<dyn Error>> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut pos = 0;
+    let nodes = parse_sequence(&chars, &mut pos, &[])?;
+    Ok(nodes)
+}
+
+fn push_char(nodes: &mut Vec<Node>, c: char) {
+    if let Some(Node::Text(text)) = nodes.last_mut() {
+        text.push(c);
+    } else {
+        nodes.push(Node::Text(c.to_string()));
+    }
+}
+
+fn parse_sequence(
+    chars: &[char],
+    pos: &mut usize,
+    stop: &[char],
+) -> Result<Vec<Node>, Box<dyn Error>> {
+    let mut nodes = Vec::new();
+
+    while *pos < chars.len() {
+        let c = chars[*pos];
+
+        if stop.contains(&c) {
+            break;
+        }
+
+        match c {
+            '\\' if *pos + 1 < chars.len() => {
+                push_char(&mut nodes, chars[*pos + 1]);
+                *pos += 2;
+            }
+            c if c.is_whitespace() => {
+                while *pos < chars.len() && chars[*pos].is_whitespace() {
+                    *pos += 1;
+                }
+                nodes.push(Node::Space);
+            }
+            '{' => match chars[*pos..].iter().position(|&c| c == '}') {
+                Some(len) => {
+                    let name: String = chars[*pos + 1..*pos + len].iter().collect();
+                    nodes.push(Node::Slot(name));
+                    *pos += len + 1;
+                }
+                None => {
+                    // Unclosed brace, treat as literal
+                    push_char(&mut nodes, c);
+                    *pos += 1;
+                }
+            },
+            '[' => {
+                *pos += 1;
+                let mut alternatives = parse_alternatives(chars, pos, ']')?;
+                alternatives.retain(|alt| !alt.is_empty());
+                if alternatives.len() <= 1 {
+                    nodes.push(Node::Optional(alternatives.pop().unwrap_or_default()));
+                } else {
+                    nodes.push(Node::Optional(vec![Node::Choice(alternatives)]));
+                }
+            }
+            '(' => {
+                *pos += 1;
+                let mut alternatives = parse_alternatives(chars, pos, ')')?;
+                if alternatives.iter().any(Vec::is_empty) {
+                    alternatives.retain(|alt| !alt.is_empty());
+                    nodes.push(Node::Optional(vec![Node::Choice(alternatives)]));
+                } else {
+                    nodes.push(Node::Choice(alternatives));
+                }
+            }
+            '*' => {
+                nodes.push(Node::Wildcard);
+                *pos += 1;
+            }
+            _ => {
+                push_char(&mut nodes, c);
+                *pos += 1;
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// The `|`-separated alternatives of a group, up to and including its `close` character.
+fn parse_alternatives(
+    chars: &[char],
+    pos: &mut usize,
+    close: char,
+) -> Result<Vec<Vec<Node>>, Box<dyn Error>> {
+    let mut alternatives = Vec::new();
+    loop {
+        alternatives.push(parse_sequence(chars, pos, &['|', close])?);
+        match chars.get(*pos) {
+            Some('|') => *pos += 1,
+            Some(&c) if c == close => {
+                *pos += 1;
+                return Ok(alternatives);
+            }
+            _ => {
+                let open = if close == ')' { '(' } else { '[' };
+                return Err(format!("Unclosed '{}' in pattern", open).into());
+            }
+        }
+    }
+}
+
+/// Builds the (unanchored) regex source for a template. `slot_regex` turns a slot name into
+/// its capture group. An optional part takes the whitespace before it along, or the one after
+/// it when it starts the sequence, so "hello [avi]" matches "hello" and "hello avi".
+pub(crate) fn to_regex<F>(nodes: &[Node], slot_regex: &F) -> Result<String, Box<dyn Error>>
+where
+    F: Fn(&str) -> Result<String, Box<dyn Error>>,
+{
+    let mut regex = String::new();
+    let mut i = 0;
+
+    while i < nodes.len() {
+        match &nodes[i] {
+            Node::Text(text) => regex.push_str(&regex::escape(text)),
+            Node::Space => {
+                if let Some(Node::Optional(inner)) = nodes.get(i + 1) {
+                    regex.push_str(&format!("(?:\\s+{})?", to_regex(inner, slot_regex)?));
+                    i += 1;
+                } else {
+                    regex.push_str("\\s+");
+                }
+            }
+            Node::Slot(name) => regex.push_str(&slot_regex(name)?),
+            Node::Optional(inner) => {
+                let inner = to_regex(inner, slot_regex)?;
+                if let Some(Node::Space) = nodes.get(i + 1) {
+                    regex.push_str(&format!("(?:{}\\s+)?", inner));
+                    i += 1;
+                } else {
+                    regex.push_str(&format!("(?:{})?", inner));
+                }
+            }
+            Node::Choice(alternatives) => {
+                let alternatives = alternatives
+                    .iter()
+                    .map(|alt| to_regex(alt, slot_regex))
+                    .collect::<Result<Vec<_>, _>>()?;
+                regex.push_str(&format!("(?:{})", alternatives.join("|")));
+            }
+            Node::Wildcard => regex.push_str("\\S+(?:\\s+\\S+)*?"),
+        }
+        i += 1;
+    }
+
+    Ok(regex)
+}
+
+/// Every concrete phrasing of a template, with `{slot}` and `*` left in place.
+/// Used by fuzzy matching, which works on plain words.
+pub(crate) fn expand(nodes: &[Node]) -> Vec<String> {
+    let mut variants = vec![String::new()];
+
+    for node in nodes {
+        let options = match node {
+            Node::Text(text) => vec![text.clone()],
+            Node::Space => vec![" ".to_string()],
+            Node::Slot(name) => vec![format!("{{{}}}", name)],
+            Node::Optional(inner) => {
+                let mut options = expand(inner);
+                options.push(String::new());
+                options
+            }
+            Node::Choice(alternatives) => alternatives.iter().flat_map(|alt| expand(alt)).collect(),
+            Node::Wildcard => vec!["*".to_string()],
+        };
+
+        variants = variants
+            .iter()
+            .flat_map(|prefix| {
+                options
+                    .iter()
+                    .map(move |option| format!("{}{}", prefix, option))
+            })
+            .take(MAX_VARIANTS)
+            .collect();
+    }
+
+    variants
+        .into_iter()
+        .map(|v| v.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn matcher(pattern: &str) -> Regex {
+        let nodes = parse(pattern).unwrap();
+        let body = to_regex(&nodes, &|name| Ok(format!("(?P<{}>.+?)", name))).unwrap();
+        Regex::new(&format!("^(?:{})$", body)).unwrap()
+    }
+
+    fn variants(pattern: &str) -> Vec<String> {
+        expand(&parse(pattern).unwrap())
+    }
+
+    #[test]
+    fn optional_words_take_their_space_along() {
+        let regex = matcher("hello [avi]");
+        assert!(regex.is_match("hello"));
+        assert!(regex.is_match("hello avi"));
+        assert!(!regex.is_match("hello "));
+
+        let regex = matcher("[please] turn on");
+        assert!(regex.is_match("turn on"));
+        assert!(regex.is_match("please turn on"));
+    }
+
+    #[test]
+    fn choices_and_empty_alternatives() {
+        let regex = matcher("(turn|switch) on");
+        assert!(regex.is_match("turn on"));
+        assert!(regex.is_match("switch on"));
+        assert!(!regex.is_match("flip on"));
+
+        assert_eq!(variants("go (now|)"), vec!["go now", "go"]);
+    }
+
+    #[test]
+    fn optional_choices() {
+        let regex = matcher("cancel [that|it]");
+        assert!(regex.is_match("cancel"));
+        assert!(regex.is_match("cancel that"));
+        assert!(regex.is_match("cancel it"));
+        assert!(!regex.is_match("cancel that|it"));
+
+        assert_eq!(
+            variants("no [thanks|thank you]"),
+            vec!["no thanks", "no thank you", "no"]
+        );
+        assert!(parse("cancel [that|it").is_err());
+    }
+
+    #[test]
+    fn slots_capture_by_name() {
+        let captures = matcher("weather in {city}")
+            .captures("weather in new york")
+            .unwrap();
+        assert_eq!(&captures["city"], "new york");
+    }
+
+    #[test]
+    fn wildcard_matches_whole_words() {
+        let regex = matcher("play * by queen");
+        assert!(regex.is_match("play we will rock you by queen"));
+        assert!(!regex.is_match("play by queen"));
+    }
+
+    #[test]
+    fn escaped_and_unclosed_characters_are_literal() {
+        assert!(matcher(r"what is 2 \* 2").is_match("what is 2 * 2"));
+        assert!(matcher("a { b").is_match("a { b"));
+    }
+
+    #[test]
+    fn unclosed_groups_are_errors() {
+        assert!(parse("hello [avi").is_err());
+        assert!(parse("(turn|switch on").is_err());
+    }
+
+    #[test]
+    fn expansion_keeps_slots_and_is_capped() {
+        assert_eq!(
+            variants("[please] (play|put on) {song}"),
+            vec![
+                "please play {song}",
+                "please put on {song}",
+                "play {song}",
+                "put on {song}"
+            ]
+        );
+
+        let huge = "(a|b|c|d) ".repeat(8);
+        assert_eq!(variants(&huge).len(), MAX_VARIANTS);
+    }
+}
@@ -1,15 +1,18 @@
 mod broker;
+mod dialogue;
 mod intent;
 mod skills;
 mod utils;
 mod version;
 
 use crate::broker::utils::start_mqtt;
+use crate::dialogue::manager::DialogueManager;
 use crate::intent::engine::IntentEngine;
 use crate::intent::recognizer::Recognizer;
 use crate::skills::manager::SkillManager;
 use crate::utils::cli;
 use crate::utils::cli::input;
+use std::time::Duration;
 /*
 Protocols:
    - Skills -> Alex
@@ -17,20 +20,77 @@ Protocols:
    - MessageBus -> (Neon (Audio | Speech) | GUI | Enclosure)
 */
 
+/// The console is a single user
+const SESSION: &str = "console";
+
+fn dialogue_manager(im: &IntentEngine) -> DialogueManager<'_> {
+    let mut dialogue = DialogueManager::new(Recognizer::new(im));
+    if let Some(seconds) = std::env::var("AVI_DIALOGUE_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+    {
+        dialogue.set_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(reprompts) = std::env::var("AVI_DIALOGUE_REPROMPTS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+    {
+        dialogue.set_max_reprompts(reprompts);
+    }
+    dialogue
+}
+
+/// Feeds the prompt word by word, the way a speech transcript arrives, acting as soon as an
+/// intent is clear. The words after it start a new transcript, and whatever is left when the
+/// prompt ends is handled as the final one.
+fn handle_streamed(
+    dialogue: &mut DialogueManager,
+    inp: &str,
+    manager: &mut SkillManager,
+) -> Vec<String> {
+    let words: Vec<&str> = inp.split_whitespace().collect();
+    let mut replies = Vec::new();
+    let mut start = 0;
+
+    for end in 1..words.len() {
+        // The trailing space marks the last word as finished
+        let partial = format!("{} ", words[start..end].join(" "));
+        if let Some(early) = dialogue.handle_partial(SESSION, &partial, manager) {
+            replies.extend(early);
+            start = end;
+        }
+    }
+
+    if start < words.len() {
+        replies.extend(dialogue.handle(SESSION, &words[start..].join(" "), manager));
+    }
+    replies
+}
+
 fn main_loop(manager: &mut SkillManager, im: &mut IntentEngine) {
-    let rec = Recognizer::new(&im);
+    let mut dialogue = dialogue_manager(im);
+    let streamed = std::env::var("AVI_STREAM_INPUT").is_ok_and(|v| v == "1");
 
     loop {
         let inp = input("Your prompt: ");
-        let matches = rec.recognize(&inp);
-        if matches.is_empty() {
-            println!("Sorry, I didn't understand.");
+
+        // Skills changed on disk are reloaded before the utterance is handled. Pending
+        // questions were asked by the old scripts, so the conversations start over
+        let changed = manager.changed_skills();
+        if !changed.is_empty() {
+            drop(dialogue);
+            manager.reload_skills(&changed, im);
+            dialogue = dialogue_manager(im);
+        }
+
+        let replies = if streamed {
+            handle_streamed(&mut dialogue, &inp, manager)
         } else {
-            for m in matches {
-                manager
-                    .process_intent(m)
-                    .expect("Error processing the intent!");
-            }
+            dialogue.handle(SESSION, &inp, manager)
+        };
+
+        for reply in replies {
+            println!("{}", reply);
         }
     }
 }
@@ -38,6 +98,42 @@ fn main_loop(manager: &mut SkillManager, im: &mut IntentEngine) {
 fn main() {
     cli::header();
     let mut im = IntentEngine::new();
+    if let Ok(language) = std::env::var("AVI_LANGUAGE") {
+        im.set_language(&language);
+    }
+    if let Some(threshold) = std::env::var("AVI_CLASSIFIER_THRESHOLD")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        && let Err(err) = im.set_classifier_threshold(threshold)
+    {
+        eprintln!("{}", err);
+    }
+    // AVI_CONJUNCTIONS_EN="and,and then,then", one variable per language
+    for (name, value) in std::env::vars() {
+        if let Some(language) = name.strip_prefix("AVI_CONJUNCTIONS_") {
+            let conjunctions = value
+                .split(',')
+                .map(str::trim)
+                .filter(|c| !c.is_empty())
+                .map(str::to_string)
+                .collect();
+            im.set_conjunctions(&language.to_lowercase(), conjunctions);
+        }
+    }
+    // Comma separated stages, prefixed with "-" to turn them off: "fold_accents,-nfkc"
+    if let Ok(stages) = std::env::var("AVI_NORMALIZER") {
+        let mut config = im.normalizer.config().clone();
+        for stage in stages.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (stage, enabled) = match stage.strip_prefix('-') {
+                Some(stage) => (stage, false),
+                None => (stage, true),
+            };
+            if let Err(err) = config.set_stage(stage, enabled) {
+                eprintln!("{}", err);
+            }
+        }
+        im.set_normalizer_config(config);
+    }
 
     let mqtt = start_mqtt();
 
@@ -45,6 +141,12 @@ fn main() {
 
     match skill_manager.load_skills_from_directory("skills", &mut im) {
         Ok(manager) => {
+            for error in manager.quarantined() {
+                eprintln!("Quarantined skill: {}", error);
+            }
+            for diagnostic in manager.diagnostics() {
+                eprintln!("Warning: {}", diagnostic);
+            }
             main_loop(manager, &mut im);
         }
         Err(e) => {
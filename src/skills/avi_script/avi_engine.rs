@@ -1,27 +1,98 @@
 use crate::skills::avi_script::engine::create_avi_script_engine;
 use crate::skills::avi_script::modules::register_modules;
-use rhai::{Engine, Scope};
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, FnPtr, FuncArgs, Scope};
 use std::env::{current_dir, set_current_dir};
 use std::error::Error;
+use std::fs;
 use std::path::{Path, PathBuf};
 
 pub fn get_avi_script_engine() -> Result<Engine, Box<dyn Error>> {
     create_avi_script_engine(register_modules)
 }
 
-pub fn run_avi_script(
+/// Runs `f` from the skill folder, where scripts resolve their imports and files. The
+/// working directory is restored even when `f` fails, the next run resolves from the root.
+/// Failing to restore it is reported instead of the result of `f`.
+fn in_skill_dir<T>(
+    skill_path: PathBuf,
+    f: impl FnOnce() -> Result<T, Box<EvalAltResult>>,
+) -> Result<T, Box<dyn Error>> {
+    let root_path = current_dir()?;
+
+    // Fails when the folder was deleted, the skill can't run anything anymore
+    set_current_dir(skill_path)?;
+
+    let result = f();
+
+    set_current_dir(Path::new(&root_path))?;
+
+    Ok(result?)
+}
+
+/// Compiles a script of the skill, with the modules it imports by a literal path resolved
+/// from the skill folder and embedded in the AST. Running it doesn't resolve them again.
+pub fn compile_avi_script(
     engine: &Engine,
     filename: &str,
     skill_path: PathBuf,
-    scope: &mut Scope,
-) -> Result<(), Box<dyn Error>> {
-    let root_path = current_dir().unwrap_or_default();
+) -> Result<AST, Box<dyn Error>> {
+    let source = fs::read_to_string(skill_path.join(filename))?;
+    let mut ast = in_skill_dir(skill_path, || {
+        engine.compile_into_self_contained(&Scope::new(), &source)
+    })?;
+    ast.set_source(filename);
+    Ok(ast)
+}
 
-    set_current_dir(skill_path).expect("Failed to set temporary CWD");
+/// Registers the modules embedded in `ast` on the engine under their import alias, where
+/// functions and callbacks called outside a run of the script find them.
+pub fn register_imports(engine: &mut Engine, ast: &AST, imports: &[(String, String)]) {
+    let Some(resolver) = ast.resolver() else {
+        return;
+    };
 
-    engine.run_file_with_scope(scope, filename.into())?;
+    // `import "path";` without an alias only runs the module
+    for (path, alias) in imports.iter().filter(|(_, alias)| !alias.is_empty()) {
+        if let Some((_, module)) = resolver.iter().find(|(embedded, _)| embedded == path) {
+            engine.register_static_module(alias, module.clone());
+        }
+    }
+}
 
-    set_current_dir(Path::new(&root_path)).expect("Failed to set temporary CWD");
+pub fn run_avi_ast(
+    engine: &Engine,
+    ast: &AST,
+    skill_path: PathBuf,
+    scope: &mut Scope,
+) -> Result<(), Box<dyn Error>> {
+    in_skill_dir(skill_path, || engine.run_ast_with_scope(scope, ast))
+}
 
-    Ok(())
+/// Calls a script-defined function of `ast` without running its top-level statements, like a
+/// slot processor.
+pub fn call_avi_script_fn(
+    engine: &Engine,
+    ast: &AST,
+    name: &str,
+    skill_path: PathBuf,
+    args: impl FuncArgs,
+) -> Result<Dynamic, Box<dyn Error>> {
+    let options = CallFnOptions::new().eval_ast(false);
+    in_skill_dir(skill_path, || {
+        engine.call_fn_with_options(options, &mut Scope::new(), ast, name, args)
+    })
+}
+
+/// Calls a function pointer kept from an earlier run of the skill's script, like an `ask`
+/// callback. `ast` is the one of that run, which holds the definitions of its closures.
+pub fn call_avi_fn(
+    engine: &Engine,
+    ast: &AST,
+    callback: &FnPtr,
+    skill_path: PathBuf,
+    args: Vec<Dynamic>,
+) -> Result<(), Box<dyn Error>> {
+    in_skill_dir(skill_path, || {
+        callback.call::<Dynamic>(engine, ast, args).map(|_| ())
+    })
 }
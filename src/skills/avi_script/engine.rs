@@ -6,57 +6,12 @@ use rhai_fs::FilesystemPackage;
 use rhai_url::UrlPackage;
 use serde_json::Value;
 
-fn on_intent_syntax_handler(
-    context: &mut EvalContext,
-    inputs: &[Expression],
+/// `on_start`, `on_end` and `on_intent` blocks are split out of `skill.avi` when the skill
+/// compiles it and run on their events, where they are written they do nothing.
+fn event_syntax_handler(
+    _context: &mut EvalContext,
+    _inputs: &[Expression],
 ) -> Result<Dynamic, Box<EvalAltResult>> {
-    let intent_name = inputs[0].get_string_value().unwrap().to_string();
-    let block = &inputs[1];
-
-    let i_name = context.scope().get_value::<ImmutableString>("INTENT_NAME");
-
-    if i_name.is_some() && i_name.unwrap().eq_ignore_ascii_case(&intent_name) {
-        let scope = context.scope_mut();
-        scope.push_constant("name", intent_name);
-        scope.push_constant(
-            "intent",
-            scope.get_value::<ExtractedSlots>("INTENT").unwrap(),
-        );
-
-        let _ = context.eval_expression_tree(block);
-    }
-
-    Ok(Dynamic::UNIT)
-}
-
-fn on_start_syntax_handler(
-    context: &mut EvalContext,
-    inputs: &[Expression],
-) -> Result<Dynamic, Box<EvalAltResult>> {
-    let block = &inputs[0];
-
-    if context.scope().get_value::<bool>("STARTED").is_none() {
-        let scope = context.scope_mut();
-        scope.push_constant("STARTED", true);
-
-        let _ = context.eval_expression_tree(block);
-    }
-
-    Ok(Dynamic::UNIT)
-}
-
-fn on_end_syntax_handler(
-    context: &mut EvalContext,
-    inputs: &[Expression],
-) -> Result<Dynamic, Box<EvalAltResult>> {
-    let block = &inputs[0];
-
-    let e_name = context.scope().get_value::<bool>("END");
-
-    if e_name.is_some() && e_name.unwrap() {
-        let _ = context.eval_expression_tree(block);
-    }
-
     Ok(Dynamic::UNIT)
 }
 
@@ -97,15 +52,17 @@ pub fn create_avi_script_engine(
 ) -> Result<Engine, Box<dyn std::error::Error>> {
     let mut engine = Engine::new();
 
+    // The blocks run apart from the statements around them, with the event's constants on
+    // the scope. `true` makes the parser look up outer variables by name inside them.
     engine.register_custom_syntax(
         ["on_intent", "$string$", "$block$"],
-        false,
-        on_intent_syntax_handler,
+        true,
+        event_syntax_handler,
     )?;
 
-    engine.register_custom_syntax(["on_start", "$block$"], false, on_start_syntax_handler)?;
+    engine.register_custom_syntax(["on_start", "$block$"], true, event_syntax_handler)?;
 
-    engine.register_custom_syntax(["on_end", "$block$"], false, on_end_syntax_handler)?;
+    engine.register_custom_syntax(["on_end", "$block$"], true, event_syntax_handler)?;
 
     engine
         .register_custom_operator("or", 160)?
@@ -130,6 +87,9 @@ pub fn create_avi_script_engine(
         .register_type_with_name::<ExtractedSlots>("Intent")
         .register_get("name", ExtractedSlots::get_name)
         .register_get("slots", ExtractedSlots::get_slots)
+        .register_get("confidence", ExtractedSlots::get_confidence)
+        .register_get("corrections", ExtractedSlots::get_corrections)
+        .register_get("language", ExtractedSlots::get_language)
         .register_fn("get", ExtractedSlots::get)
         .register_fn("get_raw", ExtractedSlots::get_raw)
         .register_fn("require", ExtractedSlots::require)
@@ -30,5 +30,9 @@ pub(crate) fn get_response(
         return Err("Intent must have at least one pattern or regex pattern".into());
     }
 
-    Ok(data.response[&lang][0].clone())
+    data.response
+        .get(&lang)
+        .and_then(|responses| responses.first())
+        .cloned()
+        .ok_or_else(|| format!("Response {} has nothing for language {}", data.id, lang).into())
 }
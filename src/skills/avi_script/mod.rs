@@ -1,6 +1,7 @@
 pub mod avi_engine;
 pub mod avi_librarymanager;
 mod engine;
-mod language;
+pub(crate) mod language;
 mod modules;
 mod net;
+pub mod script;
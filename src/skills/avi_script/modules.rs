@@ -15,16 +15,52 @@ mod speak {
     pub fn translated(key: &str, context: rhai::Map) {}
 }
 
+/// The callbacks run when the dialogue manager gets the answer, see `crate::dialogue`
 #[export_module]
 mod ask {
-    pub fn question(key: &str, callback: rhai::FnPtr, context: rhai::Map, expected: rhai::Dynamic) {
+    use crate::dialogue::ask::{AskKind, request};
+    use crate::dialogue::expected::Expected;
+
+    #[rhai_fn(return_raw)]
+    pub fn question(
+        key: &str,
+        callback: rhai::FnPtr,
+        context: rhai::Map,
+        expected: rhai::Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let key = key.to_string();
+        let expected = Expected::parse(&expected)?;
+        request(
+            AskKind::Question {
+                key,
+                context,
+                expected,
+            },
+            callback,
+        );
+        Ok(())
+    }
+
+    #[rhai_fn(return_raw)]
+    pub fn on_input(
+        callback: rhai::FnPtr,
+        expected: rhai::Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let expected = Expected::parse(&expected)?;
+        request(AskKind::Input { expected }, callback);
+        Ok(())
+    }
+
+    pub fn confirm(callback: rhai::FnPtr) {
+        request(AskKind::Confirm, callback);
+    }
+    pub fn cancel(callback: rhai::FnPtr) {
+        request(AskKind::Cancel, callback);
+    }
+    pub fn number_input(prompt: &str, callback: rhai::FnPtr) {
+        let prompt = prompt.to_string();
+        request(AskKind::Number { prompt }, callback);
     }
-
-    pub fn on_input(callback: rhai::FnPtr, expected: rhai::Dynamic) {}
-
-    pub fn confirm(callback: rhai::FnPtr) {}
-    pub fn cancel(callback: rhai::FnPtr) {}
-    pub fn number_input(prompt: &str, callback: rhai::FnPtr) {}
 }
 
 #[export_module]
@@ -0,0 +1,121 @@
+use crate::skills::avi_script::avi_engine::compile_avi_script;
+use rhai::{AST, Engine, Expr, Module, Stmt};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// `skill.avi` compiled once and split into its event handlers, so handling an intent runs
+/// only the matching `on_intent` blocks.
+pub struct SkillScript {
+    /// The whole file, its functions and closures are shared by every handler
+    ast: AST,
+    /// Top-level statements other than handlers, run once before `on_start`
+    setup: AST,
+    /// Path and alias of each top-level `import`, see [`Self::imports`]
+    imports: Vec<(String, String)>,
+    /// By event, see [`Self::handler`]
+    handlers: HashMap<String, AST>,
+}
+
+impl SkillScript {
+    pub fn compile(
+        engine: &Engine,
+        filename: &str,
+        skill_path: PathBuf,
+    ) -> Result<Self, Box<dyn Error>> {
+        // Imports are resolved once here and embedded in the AST
+        let ast = compile_avi_script(engine, filename, skill_path)?;
+
+        let mut imports = Vec::new();
+        let mut import_statements = Vec::new();
+        let mut setup = Vec::new();
+        // By event: its statements and how many of the imports before it they include
+        let mut blocks: HashMap<String, (Vec<Stmt>, usize)> = HashMap::new();
+
+        for stmt in ast.statements() {
+            if let Stmt::Import(import, _) = stmt {
+                if let Expr::StringConstant(path, _) = &import.0 {
+                    imports.push((path.to_string(), import.1.name.to_string()));
+                }
+                import_statements.push(stmt.clone());
+            }
+
+            match event_block(stmt) {
+                // A handler runs after the imports that come before it in the file, in the same
+                // order, which is how the code in it refers to the modules
+                Some((event, block)) => {
+                    let (statements, included) = blocks.entry(event).or_default();
+                    statements.extend_from_slice(&import_statements[*included..]);
+                    *included = import_statements.len();
+                    statements.push(block);
+                }
+                None => setup.push(stmt.clone()),
+            }
+        }
+
+        let compile = |statements: Vec<Stmt>| {
+            // A copy of the file's AST keeps its functions and embedded modules
+            let mut compiled = ast.clone();
+            compiled.clear_statements();
+            compiled += AST::new(statements, Module::new());
+            compiled
+        };
+
+        let handlers = blocks
+            .into_iter()
+            .map(|(event, (statements, _))| (event, compile(statements)))
+            .collect();
+
+        Ok(SkillScript {
+            setup: compile(setup),
+            imports,
+            handlers,
+            ast,
+        })
+    }
+
+    pub fn ast(&self) -> &AST {
+        &self.ast
+    }
+
+    pub fn setup(&self) -> &AST {
+        &self.setup
+    }
+
+    /// Path and alias of the modules the file imports with a literal path, embedded in
+    /// [`Self::ast`].
+    pub fn imports(&self) -> &[(String, String)] {
+        &self.imports
+    }
+
+    /// The handler of `on_start`, `on_end` or an intent, as `on_intent:<name>`. Intent names
+    /// match case-insensitively.
+    pub fn handler(&self, event: &str) -> Option<&AST> {
+        self.handlers.get(&event.to_lowercase())
+    }
+}
+
+/// The event of an `on_start`, `on_end` or `on_intent "name"` statement, as `on_start`,
+/// `on_end` or `on_intent:<name>`, and its block as a statement of its own.
+fn event_block(stmt: &Stmt) -> Option<(String, Stmt)> {
+    let Stmt::Expr(expr) = stmt else {
+        return None;
+    };
+    let Expr::Custom(custom, _) = expr.as_ref() else {
+        return None;
+    };
+
+    let event = match (custom.tokens.first()?.as_str(), custom.inputs.as_slice()) {
+        ("on_start", [_]) => "on_start".to_string(),
+        ("on_end", [_]) => "on_end".to_string(),
+        ("on_intent", [Expr::StringConstant(name, _), _]) => {
+            format!("on_intent:{}", name.to_lowercase())
+        }
+        _ => return None,
+    };
+
+    match custom.inputs.last()? {
+        Expr::Stmt(block) => Some((event, Stmt::Block(block.clone()))),
+        _ => None,
+    }
+}
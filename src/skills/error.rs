@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::fmt;
+
+/// Why a skill could not load or run. A skill failing to load is quarantined by
+/// `SkillManager`, the others keep running.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkillError {
+    /// The folder lacks one of the files or folders every skill has
+    InvalidFolder { path: String, reason: String },
+    /// `metadata.avi` failed to run or lacks a field
+    Metadata { path: String, reason: String },
+    /// `skill.avi`, or a script it imports, doesn't compile
+    ScriptCompile { skill: String, reason: String },
+    /// The script failed while setting up, in an event handler or in a callback
+    Runtime { skill: String, reason: String },
+    /// No skill has an `on_intent` handler for the intent
+    MissingHandler { intent: String },
+    /// No loaded skill has the id
+    NotLoaded { skill_id: String },
+    /// Another loaded skill already has the id
+    DuplicateId { skill_id: String, path: String },
+}
+
+impl fmt::Display for SkillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkillError::InvalidFolder { path, reason } => {
+                write!(f, "{} is not a valid skill: {}", path, reason)
+            }
+            SkillError::Metadata { path, reason } => {
+                write!(f, "Invalid metadata.avi in {}: {}", path, reason)
+            }
+            SkillError::ScriptCompile { skill, reason } => {
+                write!(
+                    f,
+                    "The script of skill {} does not compile: {}",
+                    skill, reason
+                )
+            }
+            SkillError::Runtime { skill, reason } => {
+                write!(f, "Error in skill {}: {}", skill, reason)
+            }
+            SkillError::MissingHandler { intent } => {
+                write!(f, "No skill has a handler for the intent {}", intent)
+            }
+            SkillError::NotLoaded { skill_id } => {
+                write!(f, "No skill loaded with the id {}", skill_id)
+            }
+            SkillError::DuplicateId { skill_id, path } => {
+                write!(
+                    f,
+                    "{} has the id {}, which another loaded skill already has",
+                    path, skill_id
+                )
+            }
+        }
+    }
+}
+
+impl Error for SkillError {}
@@ -1,27 +1,58 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf, absolute};
+use std::sync::mpsc::{Receiver, channel};
 
+use crate::intent::diagnostics::Diagnostic;
 use crate::intent::engine::IntentEngine;
 use crate::intent::slot_extrator::ExtractedSlots;
+use crate::skills::error::SkillError;
 use crate::skills::skill::Skill;
 use crate::skills::utils::load_skill;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rhai::{Dynamic, FnPtr};
 
 pub struct SkillManager<'a> {
     skills: Vec<Skill<'a>>,
     intent_map: HashMap<String, usize>,
+    /// Found by the intent engine after the last directory load
+    diagnostics: Vec<Diagnostic>,
+    /// Folders of the skills that failed to load, they load again once changed
+    quarantine: Vec<(PathBuf, SkillError)>,
+    /// Watches the skill folders, `None` when the platform watcher could not start
+    watcher: Option<RecommendedWatcher>,
+    changes: Receiver<notify::Result<Event>>,
 }
 
 impl<'a> SkillManager<'a> {
     pub fn new() -> Self {
+        let (sender, changes) = channel();
+        let watcher = notify::recommended_watcher(sender)
+            .inspect_err(|err| eprintln!("Skills will not be reloaded on changes: {}", err))
+            .ok();
+
         SkillManager {
             skills: Vec::new(),
             intent_map: HashMap::new(),
+            diagnostics: Vec::new(),
+            quarantine: Vec::new(),
+            watcher,
+            changes,
         }
     }
 
-    pub fn load_skill(&mut self, path: String) -> Result<&mut Skill<'a>, &'static str> {
-        self.skills.push(load_skill(path).unwrap());
+    /// Loads a skill without starting it. Its id must not be taken by a loaded skill.
+    pub fn load_skill(
+        &mut self,
+        path: String,
+        language: &str,
+    ) -> Result<&mut Skill<'a>, SkillError> {
+        let skill = load_skill(path.clone(), language)?;
+        let skill_id = skill.metadata().id.to_string();
+        if self.skill_index(&skill_id).is_some() {
+            return Err(SkillError::DuplicateId { skill_id, path });
+        }
+        self.skills.push(skill);
 
         Ok(self.skills.last_mut().unwrap())
     }
@@ -40,36 +71,143 @@ impl<'a> SkillManager<'a> {
             for entry in entries.flatten() {
                 let path = entry.path();
                 if path.is_dir() {
-                    if let Some(path_str) = path.to_str() {
-                        let path_string = path_str.to_string();
-                        match self.load_skill(path_string) {
-                            Ok(_) => {
-                                let skill_index = self.skills.len() - 1;
-                                let skill = &mut self.skills[skill_index];
-
-                                // Load intents as strings and map them to this skill
-                                let intent_names = skill.load_intents(intent_engine);
-
-                                for intent_name in intent_names {
-                                    self.intent_map.insert(intent_name, skill_index);
-                                }
-
-                                // Start the skill
-                                skill.start();
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to load skill at {:?}: {}", path, e);
-                            }
-                        }
-                    }
+                    // A skill that fails is kept in the quarantine, with its error
+                    let _ = self.add_skill(&path, intent_engine);
                 }
             }
         }
 
+        intent_engine.prepare();
+        self.diagnostics = intent_engine.analyze();
+
         Ok(self)
     }
 
-    pub fn process_intent(&mut self, slots: ExtractedSlots) -> Result<(), &'static str> {
+    /// Loads a skill with its entities and intents, starts it and watches its folder for
+    /// changes. A skill that fails is quarantined, see `quarantined`. The intent engine is
+    /// prepared by the caller once all skills are in.
+    pub fn add_skill(
+        &mut self,
+        path: &Path,
+        intent_engine: &mut IntentEngine,
+    ) -> Result<(), SkillError> {
+        // Also when the skill fails, it loads again once fixed
+        if let Some(watcher) = &mut self.watcher
+            && let Err(err) = watcher.watch(path, RecursiveMode::Recursive)
+        {
+            eprintln!("Could not watch skill at {:?}: {}", path, err);
+        }
+
+        self.start_skill(path, intent_engine).inspect_err(|err| {
+            self.quarantine.push((path.to_path_buf(), err.clone()));
+        })
+    }
+
+    fn start_skill(
+        &mut self,
+        path: &Path,
+        intent_engine: &mut IntentEngine,
+    ) -> Result<(), SkillError> {
+        let skill =
+            self.load_skill(path.to_string_lossy().to_string(), intent_engine.language())?;
+
+        let intent_names = match set_up_skill(skill, path, intent_engine) {
+            Ok(intent_names) => intent_names,
+            Err(err) => {
+                self.skills.pop();
+                return Err(err);
+            }
+        };
+
+        let skill_index = self.skills.len() - 1;
+        for intent_name in intent_names {
+            self.intent_map.insert(intent_name, skill_index);
+        }
+
+        Ok(())
+    }
+
+    /// Stops a skill and forgets it, with its intents. Its engine is freed with it, the slot
+    /// processors of its intents were the only other users.
+    pub fn unload_skill(
+        &mut self,
+        skill_id: &str,
+        intent_engine: &mut IntentEngine,
+    ) -> Result<(), SkillError> {
+        let skill_index = self
+            .skill_index(skill_id)
+            .ok_or_else(|| SkillError::NotLoaded {
+                skill_id: skill_id.to_string(),
+            })?;
+
+        let mut skill = self.skills.remove(skill_index);
+        if let Err(err) = skill.stop() {
+            eprintln!("{}", err);
+        }
+
+        for intent_name in intent_engine.skill_intents(skill_id) {
+            if self.intent_map.get(&intent_name) == Some(&skill_index) {
+                self.intent_map.remove(&intent_name);
+            }
+        }
+        intent_engine.remove_skill_intents(skill_id);
+        intent_engine.remove_skill_entities(skill_id);
+
+        // The skills after it moved down one place
+        for index in self.intent_map.values_mut() {
+            if *index > skill_index {
+                *index -= 1;
+            }
+        }
+
+        if let Some(watcher) = &mut self.watcher {
+            // Already gone when the folder was deleted
+            let _ = watcher.unwatch(&skill.get_path());
+        }
+
+        Ok(())
+    }
+
+    fn skill_index(&self, skill_id: &str) -> Option<usize> {
+        self.skills
+            .iter()
+            .position(|skill| skill.metadata().id == skill_id)
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Why the quarantined skills failed to load.
+    pub fn quarantined(&self) -> impl Iterator<Item = &SkillError> {
+        self.quarantine.iter().map(|(_, err)| err)
+    }
+
+    /// Text of a response key of the skill handling the intent.
+    pub fn response(&self, intent_name: &str, key: &str, language: &str) -> String {
+        self.intent_map
+            .get(intent_name)
+            .and_then(|&index| self.skills.get(index))
+            .map(|skill| skill.response(key, language))
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Runs an `ask` callback in the skill that handled the intent.
+    pub(crate) fn call_back(
+        &mut self,
+        intent_name: &str,
+        callback: &FnPtr,
+        args: Vec<Dynamic>,
+    ) -> Result<(), SkillError> {
+        match self.intent_map.get(intent_name) {
+            Some(&index) => self.skills[index].call_back(callback, args),
+            None => Err(SkillError::MissingHandler {
+                intent: intent_name.to_string(),
+            }),
+        }
+    }
+
+    pub fn process_intent(&mut self, slots: ExtractedSlots) -> Result<(), SkillError> {
         // Extract just the intent name string from the ExtractedSlots
         let intent_name = &slots.intent;
 
@@ -80,13 +218,446 @@ impl<'a> SkillManager<'a> {
             }
         }
 
-        Err("No skill found for this intent")
+        Err(SkillError::MissingHandler {
+            intent: intent_name.clone(),
+        })
+    }
+
+    /// Folders of the skills, loaded or quarantined, with a changed `.avi`, `.intent` or
+    /// `skill.config` file since the last call.
+    pub fn changed_skills(&self) -> Vec<PathBuf> {
+        // Not canonicalized, the folder may have been deleted
+        let folders: Vec<PathBuf> = self
+            .skills
+            .iter()
+            .map(|skill| skill.get_path())
+            .chain(self.quarantine.iter().map(|(path, _)| path.clone()))
+            .filter_map(|path| absolute(path).ok())
+            .collect();
+
+        let mut changed = Vec::new();
+        for event in self.changes.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    eprintln!("Error watching skills: {}", err);
+                    continue;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+
+            for path in event.paths.iter().filter(|path| is_skill_source(path)) {
+                if let Some(folder) = folders.iter().find(|folder| path.starts_with(folder))
+                    && !changed.contains(folder)
+                {
+                    changed.push(folder.clone());
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Reloads skills from their folders, a skill that fails to reload keeps running as it was.
+    /// Skills whose `skill.avi` is gone are unloaded, quarantined skills are loaded again.
+    pub fn reload_skills(&mut self, folders: &[PathBuf], intent_engine: &mut IntentEngine) {
+        let is_folder =
+            |path: &Path, folder: &Path| absolute(path).is_ok_and(|path| path == folder);
+
+        for folder in folders {
+            if let Some(position) = self
+                .quarantine
+                .iter()
+                .position(|(path, _)| is_folder(path, folder))
+            {
+                let (path, _) = self.quarantine.remove(position);
+                match self.start_skill(&path, intent_engine) {
+                    Ok(()) => println!("Loaded skill at {:?}", path),
+                    Err(err) => {
+                        eprintln!("Skill at {:?} is still quarantined: {}", path, err);
+                        self.quarantine.push((path, err));
+                    }
+                }
+                continue;
+            }
+
+            let Some(skill_index) = self
+                .skills
+                .iter()
+                .position(|skill| is_folder(&skill.get_path(), folder))
+            else {
+                continue;
+            };
+            let name = self.skills[skill_index].metadata().name.clone();
+
+            if !folder.join("skill.avi").is_file() {
+                let skill_id = self.skills[skill_index].metadata().id.clone();
+                if self.unload_skill(&skill_id, intent_engine).is_ok() {
+                    println!("Unloaded skill {}", name);
+                }
+                continue;
+            }
+
+            match self.reload_skill(skill_index, intent_engine) {
+                Ok(()) => println!("Reloaded skill {}", name),
+                Err(err) => eprintln!("Could not reload skill {}: {}", name, err),
+            }
+        }
+
+        intent_engine.prepare();
+        self.diagnostics = intent_engine.analyze();
+    }
+
+    fn reload_skill(
+        &mut self,
+        skill_index: usize,
+        intent_engine: &mut IntentEngine,
+    ) -> Result<(), SkillError> {
+        let path = self.skills[skill_index].get_path();
+        let mut skill = load_skill(path.to_string_lossy().to_string(), intent_engine.language())?;
+        let skill_id = skill.metadata().id.to_string();
+        if self
+            .skill_index(&skill_id)
+            .is_some_and(|index| index != skill_index)
+        {
+            return Err(SkillError::DuplicateId {
+                skill_id,
+                path: path.display().to_string(),
+            });
+        }
+
+        // The old version stops before anything of the new one runs, and starts again if the
+        // new one fails
+        if let Err(err) = self.skills[skill_index].stop() {
+            eprintln!("{}", err);
+        }
+        let old_id = self.skills[skill_index].metadata().id.clone();
+        let previous_intents = intent_engine.remove_skill_intents(&old_id);
+        let previous_entities = intent_engine.remove_skill_entities(&old_id);
+
+        let intent_names = match set_up_skill(&mut skill, &path, intent_engine) {
+            Ok(intent_names) => intent_names,
+            Err(err) => {
+                intent_engine.restore_entities(previous_entities);
+                intent_engine.restore_intents(previous_intents);
+                if let Err(err) = self.skills[skill_index].start() {
+                    eprintln!("{}", err);
+                }
+                return Err(err);
+            }
+        };
+
+        self.intent_map.retain(|_, &mut index| index != skill_index);
+        for intent_name in intent_names {
+            self.intent_map.insert(intent_name, skill_index);
+        }
+
+        self.skills[skill_index] = skill;
+
+        Ok(())
     }
 
     pub fn stop_all(&mut self) -> &mut Self {
         for skill in &mut self.skills {
-            skill.stop();
+            if let Err(err) = skill.stop() {
+                eprintln!("{}", err);
+            }
         }
         self
     }
 }
+
+/// Loads the script, entities and intents of a skill and starts it, the same way at startup
+/// and on reload, and returns its intents. A skill none of whose intents load is rejected.
+/// When it fails, the intents and entities it loaded are unloaded again.
+fn set_up_skill(
+    skill: &mut Skill,
+    path: &Path,
+    intent_engine: &mut IntentEngine,
+) -> Result<Vec<String>, SkillError> {
+    skill.load_script()?;
+
+    // Entities first, intents reference them
+    let first_intent = intent_engine.intent_count();
+    let entities = skill.load_entities(intent_engine);
+    let intent_names = skill.load_intents(intent_engine);
+
+    let started = if intent_names.is_empty() {
+        Err(SkillError::InvalidFolder {
+            path: path.display().to_string(),
+            reason: "none of its intents loaded".to_string(),
+        })
+    } else {
+        skill.start()
+    };
+    if let Err(err) = started {
+        intent_engine.remove_intents_since(first_intent);
+        intent_engine.remove_entities(&skill.metadata().id, &entities);
+        return Err(err);
+    }
+
+    Ok(intent_names)
+}
+
+/// Files whose changes reload the skill: the scripts, intents, entities and `skill.config`.
+fn is_skill_source(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == "skill.config")
+        || path
+            .extension()
+            .is_some_and(|ext| ext == "avi" || ext == "intent" || ext == "entity")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::intent::recognizer::Recognizer;
+    use std::sync::{Mutex, MutexGuard};
+
+    static WORKING_DIR: Mutex<()> = Mutex::new(());
+
+    /// Skill scripts run from their folder, so the tests that run them take turns.
+    pub(crate) fn working_dir_lock() -> MutexGuard<'static, ()> {
+        WORKING_DIR.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Writes a skill to a fresh folder: its id, intent files and `skill.avi`.
+    pub(crate) fn skill_folder(name: &str, id: &str, intents: &[&str], script: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("avi-test-{}", name));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(folder.join("intents")).unwrap();
+        fs::create_dir_all(folder.join("responses")).unwrap();
+        fs::write(
+            folder.join("metadata.avi"),
+            format!(
+                "let name = \"{}\";\nlet id = \"{}\";\nlet version = \"1.0.0\";\n\
+                 let author = \"Test\";\nlet description = \"\";\nlet languages = [\"en\"];\n\
+                 let license = \"MIT\";\n",
+                name, id
+            ),
+        )
+        .unwrap();
+        fs::write(folder.join("skill.config"), "{}").unwrap();
+        fs::write(folder.join("skill.avi"), script).unwrap();
+        update_intents(&folder, intents);
+        folder
+    }
+
+    fn update_intents(folder: &Path, intents: &[&str]) {
+        for entry in fs::read_dir(folder.join("intents")).unwrap().flatten() {
+            fs::remove_file(entry.path()).unwrap();
+        }
+        for (i, intent) in intents.iter().enumerate() {
+            fs::write(folder.join("intents").join(format!("{}.intent", i)), intent).unwrap();
+        }
+    }
+
+    fn intent(name: &str, pattern: &str) -> String {
+        format!(r#"{{"intent": "{}", "patterns": ["{}"]}}"#, name, pattern)
+    }
+
+    fn handler(name: &str) -> String {
+        format!("on_intent \"{}\" {{ }}\n", name)
+    }
+
+    fn recognized(engine: &IntentEngine, text: &str) -> Option<String> {
+        Recognizer::new(engine)
+            .recognize_best(text)
+            .map(|m| m.intent)
+    }
+
+    fn dispatch(skills: &mut SkillManager, engine: &IntentEngine, text: &str) -> bool {
+        Recognizer::new(engine)
+            .recognize_best(text)
+            .is_some_and(|m| skills.process_intent(m).is_ok())
+    }
+
+    #[test]
+    fn unloading_removes_the_skill_and_its_intents() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let greet = skill_folder(
+            "unload-a",
+            "a.skill",
+            &[&intent("greet", "hello")],
+            &handler("greet"),
+        );
+        let leave = skill_folder(
+            "unload-b",
+            "b.skill",
+            &[&intent("leave", "bye")],
+            &handler("leave"),
+        );
+        skills.add_skill(&greet, &mut engine).unwrap();
+        skills.add_skill(&leave, &mut engine).unwrap();
+
+        skills.unload_skill("a.skill", &mut engine).unwrap();
+        assert_eq!(recognized(&engine, "hello"), None);
+        assert!(dispatch(&mut skills, &engine, "bye"));
+        assert_eq!(
+            skills.unload_skill("a.skill", &mut engine),
+            Err(SkillError::NotLoaded {
+                skill_id: "a.skill".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn reloading_replaces_the_intents() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let folder = skill_folder(
+            "reload",
+            "r.skill",
+            &[&intent("greet", "hello")],
+            &handler("greet"),
+        );
+        skills.add_skill(&folder, &mut engine).unwrap();
+
+        update_intents(&folder, &[&intent("wave", "wave")]);
+        fs::write(folder.join("skill.avi"), handler("wave")).unwrap();
+        skills.reload_skills(std::slice::from_ref(&folder), &mut engine);
+
+        assert_eq!(recognized(&engine, "hello"), None);
+        assert!(dispatch(&mut skills, &engine, "wave"));
+    }
+
+    #[test]
+    fn a_failed_reload_keeps_the_old_version() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let folder = skill_folder(
+            "failed",
+            "f.skill",
+            &[&intent("greet", "hello")],
+            &handler("greet"),
+        );
+        skills.add_skill(&folder, &mut engine).unwrap();
+
+        update_intents(&folder, &[&intent("wave", "wave")]);
+        fs::write(folder.join("skill.avi"), "on_start { throw \"broken\"; }").unwrap();
+        skills.reload_skills(std::slice::from_ref(&folder), &mut engine);
+
+        assert!(dispatch(&mut skills, &engine, "hello"));
+        assert_eq!(recognized(&engine, "wave"), None);
+        assert_eq!(engine.skill_intents("f.skill"), vec!["greet"]);
+    }
+
+    #[test]
+    fn broken_skills_are_quarantined_until_fixed() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let working = skill_folder(
+            "quarantine-ok",
+            "ok.skill",
+            &[&intent("greet", "hello")],
+            &handler("greet"),
+        );
+        let uncompiled = skill_folder(
+            "quarantine-script",
+            "script.skill",
+            &[&intent("wave", "wave")],
+            "on_intent \"wave\" {",
+        );
+        let no_metadata = skill_folder(
+            "quarantine-metadata",
+            "metadata.skill",
+            &[&intent("leave", "bye")],
+            &handler("leave"),
+        );
+        fs::write(no_metadata.join("metadata.avi"), "let name = \"x\";").unwrap();
+
+        skills.add_skill(&working, &mut engine).unwrap();
+        assert!(matches!(
+            skills.add_skill(&uncompiled, &mut engine),
+            Err(SkillError::ScriptCompile { .. })
+        ));
+        assert!(matches!(
+            skills.add_skill(&no_metadata, &mut engine),
+            Err(SkillError::Metadata { .. })
+        ));
+        engine.prepare();
+
+        assert_eq!(skills.quarantined().count(), 2);
+        assert!(dispatch(&mut skills, &engine, "hello"));
+        assert_eq!(recognized(&engine, "wave"), None);
+        assert_eq!(recognized(&engine, "bye"), None);
+
+        fs::write(uncompiled.join("skill.avi"), handler("wave")).unwrap();
+        skills.reload_skills(&[uncompiled, no_metadata], &mut engine);
+        assert_eq!(skills.quarantined().count(), 1);
+        assert!(dispatch(&mut skills, &engine, "wave"));
+    }
+
+    #[test]
+    fn skills_without_a_loaded_intent_are_rejected_at_startup_and_on_reload() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let broken = r#"{"intent": "greet", "patterns": []}"#;
+        let folder = skill_folder("no-intents", "n.skill", &[broken], &handler("greet"));
+
+        assert!(matches!(
+            skills.add_skill(&folder, &mut engine),
+            Err(SkillError::InvalidFolder { .. })
+        ));
+        assert_eq!(skills.quarantined().count(), 1);
+
+        update_intents(&folder, &[&intent("greet", "hello")]);
+        skills.reload_skills(std::slice::from_ref(&folder), &mut engine);
+        assert_eq!(skills.quarantined().count(), 0);
+
+        update_intents(&folder, &[broken]);
+        skills.reload_skills(std::slice::from_ref(&folder), &mut engine);
+        assert!(dispatch(&mut skills, &engine, "hello"));
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected_without_touching_the_loaded_skill() {
+        let _lock = working_dir_lock();
+        let mut engine = IntentEngine::new();
+        let mut skills = SkillManager::new();
+        let first = skill_folder(
+            "dup-a",
+            "dup.skill",
+            &[&intent("greet", "hello")],
+            &handler("greet"),
+        );
+        let second = skill_folder(
+            "dup-b",
+            "dup.skill",
+            &[&intent("wave", "wave")],
+            &handler("wave"),
+        );
+        let other = skill_folder(
+            "dup-c",
+            "other.skill",
+            &[&intent("leave", "bye")],
+            &handler("leave"),
+        );
+        skills.add_skill(&first, &mut engine).unwrap();
+        skills.add_skill(&other, &mut engine).unwrap();
+
+        assert!(matches!(
+            skills.add_skill(&second, &mut engine),
+            Err(SkillError::DuplicateId { .. })
+        ));
+        assert!(dispatch(&mut skills, &engine, "hello"));
+        assert_eq!(recognized(&engine, "wave"), None);
+
+        // A reload that takes the id of another skill fails the same way
+        fs::write(
+            other.join("metadata.avi"),
+            fs::read_to_string(first.join("metadata.avi")).unwrap(),
+        )
+        .unwrap();
+        skills.reload_skills(std::slice::from_ref(&other), &mut engine);
+        assert!(dispatch(&mut skills, &engine, "hello"));
+        assert!(dispatch(&mut skills, &engine, "bye"));
+    }
+}
@@ -1,4 +1,5 @@
-mod avi_script;
+pub(crate) mod avi_script;
+pub mod error;
 pub mod manager;
 pub mod skill;
 mod skill_metadata;
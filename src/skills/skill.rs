@@ -25,54 +25,138 @@ language = ["en", "es"]
 license = "MIT"
 ```
 */
-use crate::intent::engine::IntentEngine;
+use crate::intent::engine::{IntentEngine, SkillContext};
+use crate::intent::processor::ProcessorLoader;
 use crate::intent::slot_extrator::ExtractedSlots;
-use crate::skills::avi_script::avi_engine::{get_avi_script_engine, run_avi_script};
+use crate::skills::avi_script::avi_engine::{
+    call_avi_fn, get_avi_script_engine, register_imports, run_avi_ast,
+};
+use crate::skills::avi_script::language::response::get_response;
+use crate::skills::avi_script::script::SkillScript;
+use crate::skills::error::SkillError;
 use crate::skills::skill_metadata::SkillMetadata;
-use rhai::{Engine, Scope};
+use rhai::{Dynamic, Engine, FnPtr, Scope};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 pub struct Skill<'a> {
     path: String,
+    /// `path` made absolute when the skill is created, intents and entities load from it
+    folder: PathBuf,
     metadata: SkillMetadata,
-    engine: Engine,
+    /// Shared with the AviScript slot processors of the skill's intents
+    engine: Rc<Engine>,
     scope: Scope<'a>,
+    /// `skill.avi`, compiled when the skill starts
+    script: Option<SkillScript>,
 }
 
 impl<'a> Skill<'a> {
-    pub(crate) fn new(path: &str, metadata: SkillMetadata, scope: Scope<'a>) -> Self {
-        let engine = get_avi_script_engine().unwrap();
+    pub(crate) fn new(
+        path: &str,
+        metadata: SkillMetadata,
+        scope: Scope<'a>,
+    ) -> Result<Self, SkillError> {
+        let engine = get_avi_script_engine().map_err(|err| SkillError::Runtime {
+            skill: metadata.name.to_string(),
+            reason: format!("could not create the script engine: {}", err),
+        })?;
+        let folder = std::env::current_dir()
+            .map_err(|err| SkillError::InvalidFolder {
+                path: path.to_string(),
+                reason: format!("could not resolve the working directory: {}", err),
+            })?
+            .join(path);
 
-        Skill {
+        Ok(Skill {
             path: path.to_string(),
+            folder,
             metadata,
-            engine,
+            engine: Rc::new(engine),
             scope,
-        }
+            script: None,
+        })
     }
 
     pub(crate) fn metadata(&self) -> &SkillMetadata {
         &self.metadata
     }
 
-    pub(crate) fn start(&mut self) {
-        run_avi_script(&self.engine, "skill.avi", self.get_path(), &mut self.scope)
-            .expect("Skill Start error!!");
+    /// Compiles `skill.avi` and runs its statements outside the handlers.
+    pub(crate) fn load_script(&mut self) -> Result<(), SkillError> {
+        let script =
+            SkillScript::compile(&self.engine, "skill.avi", self.get_path()).map_err(|err| {
+                SkillError::ScriptCompile {
+                    skill: self.metadata.name.to_string(),
+                    reason: err.to_string(),
+                }
+            })?;
+
+        // Processors hold the engine once the intents are loaded, imports are registered first
+        match Rc::get_mut(&mut self.engine) {
+            Some(engine) => register_imports(engine, script.ast(), script.imports()),
+            None => return Err(self.runtime_error("the script engine is already in use")),
+        }
+
+        run_avi_ast(
+            &self.engine,
+            script.setup(),
+            self.get_path(),
+            &mut self.scope,
+        )
+        .map_err(|err| self.runtime_error(err))?;
+
+        self.script = Some(script);
+        Ok(())
+    }
+
+    pub(crate) fn start(&mut self) -> Result<(), SkillError> {
+        self.run_event("on_start")
     }
 
     pub(crate) fn load_intents(&mut self, intent_engine: &mut IntentEngine) -> Vec<String> {
         let name = self.metadata.name.clone();
-        let intents_path = std::env::current_dir()
-            .unwrap()
-            .join(self.get_path())
-            .join("intents");
+        let intents_path = self.folder.join("intents");
 
         let mut extracted_names = Vec::<String>::new();
+        let processors = ProcessorLoader::new(self.engine.clone(), self.folder.clone());
+
+        let languages = self.metadata.language_codes();
+        let context = SkillContext {
+            skill_id: &self.metadata.id,
+            languages: &languages,
+            processors: &processors,
+        };
 
         if let Ok(entries) = fs::read_dir(&intents_path) {
+            // Intent files directly in `intents/`, or in `intents/<lang>/` for one language
+            let mut files = Vec::new();
             for entry in entries.flatten() {
-                match intent_engine.load_intent(entry.path()) {
+                let path = entry.path();
+                if !path.is_dir() {
+                    files.push((path, None));
+                    continue;
+                }
+
+                let language = entry.file_name().to_string_lossy().to_string();
+                if !languages.contains(&language) {
+                    eprintln!(
+                        "Skipping intents/{} of skill {}, the language is not in its metadata",
+                        language, name
+                    );
+                    continue;
+                }
+
+                if let Ok(lang_entries) = fs::read_dir(&path) {
+                    for lang_entry in lang_entries.flatten() {
+                        files.push((lang_entry.path(), Some(language.clone())));
+                    }
+                }
+            }
+
+            for (path, language) in files {
+                match intent_engine.load_intent(&path, language.as_deref(), &context) {
                     Ok(slots) => {
                         extracted_names.push(slots);
                     }
@@ -92,24 +176,245 @@ impl<'a> Skill<'a> {
         extracted_names
     }
 
+    pub(crate) fn load_entities(&mut self, intent_engine: &mut IntentEngine) -> Vec<String> {
+        let name = self.metadata.name.clone();
+        let entities_path = self.folder.join("entities");
+
+        let mut loaded_names = Vec::<String>::new();
+
+        // Entities are optional, a skill without the directory has none
+        if let Ok(entries) = fs::read_dir(&entities_path) {
+            for entry in entries.flatten() {
+                match intent_engine.load_entity(entry.path(), &self.metadata.id) {
+                    Ok(entity) => {
+                        loaded_names.push(entity);
+                    }
+                    Err(err) => {
+                        eprintln!("Error importing entities for skill {}: {}", name, err);
+                        continue;
+                    }
+                }
+            }
+        }
+
+        loaded_names
+    }
+
+    /// Runs a callback the skill registered with the `ask` module.
+    pub(crate) fn call_back(
+        &mut self,
+        callback: &FnPtr,
+        args: Vec<Dynamic>,
+    ) -> Result<(), SkillError> {
+        let Some(script) = &self.script else {
+            return Err(self.runtime_error("the skill has not started"));
+        };
+
+        call_avi_fn(&self.engine, script.ast(), callback, self.get_path(), args)
+            .map_err(|err| self.runtime_error(err))
+    }
+
+    /// Text of a response in `responses/<key>.resp`, or the key itself when there is none.
+    pub(crate) fn response(&self, key: &str, language: &str) -> String {
+        let responses = self.get_path().join("responses");
+        get_response(
+            responses.to_string_lossy().to_string(),
+            format!("{}.resp", key),
+            language.to_string(),
+        )
+        .unwrap_or_else(|_| key.to_string())
+    }
+
     pub(crate) fn get_path(&self) -> PathBuf {
         Path::new(self.path.as_str()).to_path_buf()
     }
 
-    pub(crate) fn stop(&mut self) {
-        self.scope.push_constant("END", true);
-        run_avi_script(&self.engine, "skill.avi", self.get_path(), &mut self.scope)
-            .expect("Skill End error!!");
+    pub(crate) fn stop(&mut self) -> Result<(), SkillError> {
+        self.run_event("on_end")
     }
 
-    pub(crate) fn on_intent(&mut self, intent: ExtractedSlots) -> Result<(), &'static str> {
+    pub(crate) fn on_intent(&mut self, intent: ExtractedSlots) -> Result<(), SkillError> {
+        let Some(script) = &self.script else {
+            return Err(self.runtime_error("the skill has not started"));
+        };
+        let Some(handler) = script.handler(&format!("on_intent:{}", intent.intent)) else {
+            return Err(SkillError::MissingHandler {
+                intent: intent.intent,
+            });
+        };
+
+        // The intent is only in scope for this call, the skill's own variables below it keep
+        // their values from one intent to the next
+        let state_len = self.scope.len();
         self.scope
+            .push_constant("CURRENT_LANGUAGE", intent.language.clone())
             .push_constant("INTENT_NAME", intent.intent.clone())
-            .push_constant("INTENT", intent.clone());
+            .push_constant("INTENT", intent.clone())
+            .push_constant("name", intent.intent.clone())
+            .push_constant("intent", intent);
 
-        run_avi_script(&self.engine, "skill.avi", self.get_path(), &mut self.scope)
-            .expect("Skill error!!");
+        let result = run_avi_ast(&self.engine, handler, self.get_path(), &mut self.scope);
+        self.scope.rewind(state_len);
 
-        Ok(())
+        result.map_err(|err| self.runtime_error(err))
+    }
+
+    /// Runs the `on_start` or `on_end` handler, if the script has one.
+    fn run_event(&mut self, event: &str) -> Result<(), SkillError> {
+        let Some(handler) = self
+            .script
+            .as_ref()
+            .and_then(|script| script.handler(event))
+        else {
+            return Ok(());
+        };
+
+        run_avi_ast(&self.engine, handler, self.get_path(), &mut self.scope)
+            .map_err(|err| self.runtime_error(format!("{}: {}", event, err)))
+    }
+
+    fn runtime_error(&self, reason: impl ToString) -> SkillError {
+        SkillError::Runtime {
+            skill: self.metadata.name.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::manager::tests::{skill_folder, working_dir_lock};
+    use crate::skills::utils::load_skill;
+    use std::collections::HashMap;
+
+    const SCRIPT: &str = r#"
+import "helpers" as h;
+let setups = 0;
+setups += 1;
+let greetings = 0;
+
+on_start {
+    if setups != 1 { throw "setup ran again"; }
+}
+
+on_intent "greet" {
+    greetings += 1;
+    if setups != 1 { throw "setup ran again"; }
+    if h::twice(greetings) != greetings * 2 { throw "helpers is not imported"; }
+    if INTENT_NAME != "greet" { throw "wrong intent"; }
+}
+
+on_intent "count" {
+    if greetings != 2 { throw `greeted ${greetings} times`; }
+}
+
+on_intent "fail" {
+    throw "the handler of fail ran";
+}
+
+import "later" as l;
+
+on_intent "later" {
+    if l::value() != 42 { throw "later is not imported"; }
+}
+"#;
+
+    fn intent(name: &str) -> ExtractedSlots {
+        ExtractedSlots {
+            intent: name.to_string(),
+            slots: HashMap::new(),
+            confidence: 1.0,
+            corrections: HashMap::new(),
+            language: "en".to_string(),
+        }
+    }
+
+    fn started_skill(name: &str, script: &str) -> Skill<'static> {
+        let folder = skill_folder(
+            name,
+            "script.skill",
+            &[r#"{"intent": "greet", "patterns": ["hello"]}"#],
+            script,
+        );
+        fs::write(folder.join("helpers.avi"), "fn twice(x) { x * 2 }").unwrap();
+        fs::write(folder.join("later.avi"), "fn value() { 42 }").unwrap();
+
+        let mut skill = load_skill(folder.to_string_lossy().to_string(), "en").unwrap();
+        skill.load_script().unwrap();
+        skill.start().unwrap();
+        skill
+    }
+
+    #[test]
+    fn setup_runs_once_and_keeps_its_variables() {
+        let _lock = working_dir_lock();
+        let mut skill = started_skill("script-setup", SCRIPT);
+
+        skill.on_intent(intent("greet")).unwrap();
+        skill.on_intent(intent("greet")).unwrap();
+        skill.on_intent(intent("count")).unwrap();
+    }
+
+    #[test]
+    fn only_the_matching_handler_runs() {
+        let _lock = working_dir_lock();
+        let mut skill = started_skill("script-dispatch", SCRIPT);
+
+        // The modules were embedded when the script compiled
+        fs::remove_file(skill.get_path().join("helpers.avi")).unwrap();
+        fs::remove_file(skill.get_path().join("later.avi")).unwrap();
+
+        skill.on_intent(intent("greet")).unwrap();
+        skill.on_intent(intent("later")).unwrap();
+        assert!(matches!(
+            skill.on_intent(intent("fail")),
+            Err(SkillError::Runtime { reason, .. }) if reason.contains("the handler of fail ran")
+        ));
+        assert_eq!(
+            skill.on_intent(intent("missing")),
+            Err(SkillError::MissingHandler {
+                intent: "missing".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn handler_variables_only_last_for_the_call() {
+        let _lock = working_dir_lock();
+        let mut skill = started_skill(
+            "script-scope",
+            r#"
+let count = 0;
+
+on_intent "greet" {
+    let temporary = INTENT_NAME;
+    count += 1;
+}
+
+on_intent "check" {
+    if is_def_var("temporary") { throw "temporary outlived its call"; }
+    if count != 1 { throw `count is ${count}`; }
+}
+
+on_intent "fail" {
+    let temporary = 1;
+    throw "failed";
+}
+"#,
+        );
+        let state_len = skill.scope.len();
+
+        skill.on_intent(intent("greet")).unwrap();
+        assert_eq!(skill.scope.len(), state_len);
+        assert!(!skill.scope.contains("intent"));
+        skill.on_intent(intent("check")).unwrap();
+
+        // The call fails halfway and its variables still go
+        assert!(matches!(
+            skill.on_intent(intent("fail")),
+            Err(SkillError::Runtime { .. })
+        ));
+        assert_eq!(skill.scope.len(), state_len);
     }
 }
@@ -1,4 +1,5 @@
 use crate::skills::avi_script::avi_engine::get_avi_script_engine;
+use crate::skills::error::SkillError;
 use rhai::{Array, ImmutableString, Scope};
 use std::path::{Path, PathBuf};
 
@@ -13,22 +14,43 @@ pub struct SkillMetadata {
 }
 
 impl SkillMetadata {
-    pub fn load(path: &Path) -> SkillMetadata {
+    pub fn load(path: &Path) -> Result<SkillMetadata, SkillError> {
+        let error = |reason: String| SkillError::Metadata {
+            path: path.display().to_string(),
+            reason,
+        };
+
         let mut scope = Scope::new();
-        let engine = get_avi_script_engine().unwrap();
+        let engine = get_avi_script_engine().map_err(|err| error(err.to_string()))?;
 
         engine
             .run_file_with_scope(&mut scope, PathBuf::from(path).join("metadata.avi"))
-            .expect("Error running metadata.avi!");
+            .map_err(|err| error(err.to_string()))?;
+
+        let text = |field: &str| {
+            scope
+                .get_value::<ImmutableString>(field)
+                .ok_or_else(|| error(format!("{} is missing or not a string", field)))
+        };
+
+        Ok(SkillMetadata {
+            name: text("name")?,
+            id: text("id")?,
+            version: text("version")?,
+            author: text("author")?,
+            description: text("description")?,
+            languages: scope
+                .get_value::<Array>("languages")
+                .ok_or_else(|| error("languages is missing or not an array".to_string()))?,
+            license: text("license")?,
+        })
+    }
 
-        SkillMetadata {
-            name: scope.get_value::<ImmutableString>("name").unwrap(),
-            id: scope.get_value::<ImmutableString>("id").unwrap(),
-            version: scope.get_value::<ImmutableString>("version").unwrap(),
-            author: scope.get_value::<ImmutableString>("author").unwrap(),
-            description: scope.get_value::<ImmutableString>("description").unwrap(),
-            languages: scope.get_value::<Array>("languages").unwrap(),
-            license: scope.get_value::<ImmutableString>("license").unwrap(),
-        }
+    /// `languages` as plain strings, anything that is not a string is left out.
+    pub fn language_codes(&self) -> Vec<String> {
+        self.languages
+            .iter()
+            .filter_map(|l| l.clone().into_string().ok())
+            .collect()
     }
 }
@@ -1,31 +1,50 @@
+use crate::skills::error::SkillError;
 use crate::skills::skill::Skill;
 use crate::skills::skill_metadata::SkillMetadata;
 use rhai::{Array, Scope};
 use std::path::Path;
 
-fn is_valid_skill_folder(path: &str) -> bool {
+fn check_skill_folder(path: &str) -> Result<(), SkillError> {
+    let invalid = |reason: &str| SkillError::InvalidFolder {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    };
+
     let folder = Path::new(path);
     if !folder.exists() || !folder.is_dir() {
-        return false;
+        return Err(invalid("not a folder"));
     }
 
     let required_folders = vec!["intents", "responses"];
     for req_folder in required_folders {
         if !folder.join(req_folder).exists() || !folder.join(req_folder).is_dir() {
-            return false;
+            return Err(invalid(&format!("{}/ is missing", req_folder)));
         }
     }
 
     let required_files = vec!["skill.avi", "metadata.avi", "skill.config"];
     for req_files in required_files {
         if !folder.join(req_files).exists() || !folder.join(req_files).is_file() {
-            return false;
+            return Err(invalid(&format!("{} is missing", req_files)));
         }
     }
 
+    if !has_intent_file(&folder.join("intents"), true) {
+        return Err(invalid("intents/ has no .intent file"));
+    }
+
+    Ok(())
+}
+
+/// Looks for `.intent` files, and one level down in `intents/<lang>/` when `nested` is set.
+fn has_intent_file(folder: &Path, nested: bool) -> bool {
     let mut valid = false;
-    if let Ok(entries) = folder.join("intents").read_dir() {
+    if let Ok(entries) = folder.read_dir() {
         for entry in entries.flatten() {
+            if nested && entry.path().is_dir() && has_intent_file(&entry.path(), false) {
+                valid = true;
+                break;
+            }
             if let Some(ext) = entry.path().extension() {
                 if ext == "intent" {
                     valid = true;
@@ -38,26 +57,26 @@ fn is_valid_skill_folder(path: &str) -> bool {
     valid
 }
 
-pub fn load_skill(path: String) -> Result<Skill<'static>, &'static str> {
-    if !is_valid_skill_folder(&path) {
-        return Err("Not a valid skill!");
-    }
+pub fn load_skill(path: String, language: &str) -> Result<Skill<'static>, SkillError> {
+    check_skill_folder(&path)?;
 
     let folder = Path::new(&path);
 
-    let metadata = SkillMetadata::load(folder);
+    let metadata = SkillMetadata::load(folder)?;
 
     let mut scope = Scope::new();
 
-    let supported_languages: Array = vec!["pt".into(), "en".into()];
+    let supported_languages: Array = metadata.languages.clone();
 
     scope
         .push_constant("SKILL_NAME", metadata.name.clone())
         .push_constant("SKILL_ID", metadata.id.clone())
         .push_constant("SKILL_VERSION", metadata.version.clone())
         .push_constant("SKILL_AUTHOR", metadata.author.clone())
-        .push_constant("CURRENT_LANGUAGE", "pt")
+        .push_constant("SKILL_DESCRIPTION", metadata.description.clone())
+        .push_constant("SKILL_LICENSE", metadata.license.clone())
+        .push_constant("CURRENT_LANGUAGE", language.to_string())
         .push_constant("SUPPORTED_LANGUAGES", supported_languages);
 
-    Ok(Skill::new(&path, metadata, scope))
+    Skill::new(&path, metadata, scope)
 }